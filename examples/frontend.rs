@@ -0,0 +1,63 @@
+// The skeleton of a frontend of your own: a hot-seat game where every
+// player types orders like "A B 5" and ends their turn with an empty line.
+// Showing the game and reading input are the parts to replace.
+
+extern crate clonequest;
+extern crate rand;
+
+use std::io::{self, BufRead};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::commentary::{MessageFormatter, PlainFormatter};
+use clonequest::game::{Game, GameRules, Player};
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let players = vec![Player { name: "Alice".into(), team: None }, Player { name: "Bob".into(), team: None }];
+    let mut game = Game::new(6, 6, players, 4, GameRules::default(), &mut rng).expect("Could not create the game");
+    let mut formatter = PlainFormatter;
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    while game.get_winner().is_none() {
+        for player_id in game.remaining_players() {
+            let name = game.player(player_id).map(|p| p.name.clone()).unwrap_or_default();
+            println!("Turn {}: {} to move", game.turn(), name);
+            for (_, planet) in game.planets().filter(|(id, _)| game.visible(player_id, *id)) {
+                let owner = planet.owner.and_then(|owner| game.player(owner)).map(|p| p.name.as_str()).unwrap_or("neutral");
+                println!("  {} at {:?}: {} ships, {}", planet.name, planet.pos, planet.ships, owner);
+            }
+            loop {
+                let line = match lines.next() {
+                    Some(Ok(line)) => line,
+                    _ => return,
+                };
+                let words: Vec<&str> = line.split_whitespace().collect();
+                if words.is_empty() {
+                    break;
+                }
+                let order = match words.as_slice() {
+                    [source, destination, count] => game.get_planet_id(source).ok()
+                        .zip(game.get_planet_id(destination).ok())
+                        .zip(count.parse().ok()),
+                    _ => None,
+                };
+                match order {
+                    Some(((source, destination), count)) => {
+                        if let Err(e) = game.queue_fleet(player_id, source, destination, count, None) {
+                            println!("Could not send the fleet: {:?}", e);
+                        }
+                    }
+                    None => println!("Orders look like: A B 5"),
+                }
+            }
+        }
+        for event in game.end_turn(&mut rng) {
+            println!("{}", formatter.format(&game, &event.message));
+        }
+    }
+    if let Some(winner) = game.get_winner() {
+        println!("{} wins!", game.winner_name(winner));
+    }
+}
@@ -0,0 +1,51 @@
+// Plays a series of games between two of the built-in bots without any
+// output but the results, the way a tournament or a balance test would.
+// An observer counts how often planets change hands along the way.
+
+extern crate clonequest;
+extern crate rand;
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+use clonequest::ai::{DefensiveBot, LookaheadBot};
+use clonequest::controller::PlayerController;
+use clonequest::game::{Event, Game, GameRules, Message, Player, PlayerId};
+use clonequest::headless::play_headless;
+use clonequest::observer::GameObserver;
+
+const GAMES: usize = 5;
+const TURN_LIMIT: usize = 200;
+
+// Shares its count with main, which keeps the other handle
+struct CaptureCounter(Rc<Cell<usize>>);
+
+impl GameObserver for CaptureCounter {
+    fn turn_resolved(&mut self, _game: &Game, events: &[Event]) {
+        let captures = events.iter().filter(|event| matches!(&event.message, Message::Battle(battle) if battle.captured)).count();
+        self.0.set(self.0.get() + captures);
+    }
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(42);
+    let players = vec![Player { name: "Defensive".into(), team: None }, Player { name: "Lookahead".into(), team: None }];
+    for game_number in 1..=GAMES {
+        let mut game = Game::new(8, 8, players.clone(), 5, GameRules::default(), &mut rng).expect("Could not create the game");
+        let captures = Rc::new(Cell::new(0));
+        game.subscribe(Box::new(CaptureCounter(captures.clone())));
+        let ids: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+        let mut controllers: HashMap<PlayerId, Box<dyn PlayerController>> = HashMap::new();
+        controllers.insert(ids[0], Box::new(DefensiveBot));
+        controllers.insert(ids[1], Box::new(LookaheadBot::new(rng.gen())));
+        let result = match play_headless(&mut game, &mut controllers, TURN_LIMIT, &mut rng) {
+            Some(winner) => format!("{} won after {} turns", game.winner_name(winner), game.turn() - 1),
+            None => "draw".to_string(),
+        };
+        println!("Game {}: {}, {} planets captured", game_number, result, captures.get());
+    }
+}
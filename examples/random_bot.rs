@@ -0,0 +1,46 @@
+// A bot written against the library: it sends half of the ships on each of
+// its planets to a random planet, and plays a game against the greedy bot.
+
+extern crate clonequest;
+extern crate rand;
+
+use std::collections::HashMap;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+
+use clonequest::ai::GreedyBot;
+use clonequest::controller::PlayerController;
+use clonequest::game::{Game, GameRules, Player, PlayerId, SendShipsCommand};
+use clonequest::headless::{DEFAULT_TURN_LIMIT, play_headless};
+
+struct HalfRandomBot {
+    rng: StdRng,
+}
+
+impl PlayerController for HalfRandomBot {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand> {
+        let mut orders = Vec::new();
+        for (source, planet) in view.planets().filter(|(_, p)| p.owner == Some(me) && p.ships >= 2) {
+            if let Some((destination, _)) = view.planets().filter(|(id, _)| *id != source).choose(&mut self.rng) {
+                orders.push(SendShipsCommand { source_planet_id: source, destination_planet_id: destination, count: planet.ships / 2, name: None });
+            }
+        }
+        orders
+    }
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let players = vec![Player { name: "Random".into(), team: None }, Player { name: "Greedy".into(), team: None }];
+    let mut game = Game::new(8, 8, players, 6, GameRules::default(), &mut rng).expect("Could not create the game");
+    let ids: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+    let mut controllers: HashMap<PlayerId, Box<dyn PlayerController>> = HashMap::new();
+    controllers.insert(ids[0], Box::new(HalfRandomBot { rng: StdRng::seed_from_u64(2) }));
+    controllers.insert(ids[1], Box::new(GreedyBot));
+    match play_headless(&mut game, &mut controllers, DEFAULT_TURN_LIMIT, &mut rng) {
+        Some(winner) => println!("{} won on turn {}", game.winner_name(winner), game.turn() - 1),
+        None => println!("Nobody won within {} turns", DEFAULT_TURN_LIMIT),
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::controller::PlayerController;
+use crate::game::{Game, PlanetId, PlayerId, SendShipsCommand, distance};
+
+fn order(source: PlanetId, destination: PlanetId, count: usize) -> SendShipsCommand {
+    SendShipsCommand {
+        source_planet_id: source,
+        destination_planet_id: destination,
+        count,
+        name: None,
+    }
+}
+
+// Sends random numbers of ships from its planets to random targets
+pub struct RandomBot {
+    rng: StdRng,
+}
+
+impl RandomBot {
+    pub fn new(seed: u64) -> RandomBot {
+        RandomBot { rng: StdRng::seed_from_u64(seed) }
+    }
+}
+
+impl PlayerController for RandomBot {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand> {
+        let targets: Vec<PlanetId> = view.planets().map(|(id, _)| id).collect();
+        let mut commands = Vec::new();
+        for (source, planet) in view.planets().filter(|(_, p)| p.owner == Some(me) && p.ships > 0) {
+            // Leave some planets alone to build up
+            if self.rng.gen_bool(0.3) {
+                continue;
+            }
+            let destination = *targets.choose(&mut self.rng).expect("Games have planets");
+            let count = self.rng.gen_range(1..=planet.ships);
+            if destination != source && view.check_fleet(me, source, destination, count, None).is_ok() {
+                commands.push(order(source, destination, count));
+            }
+        }
+        commands
+    }
+}
+
+// Smallest fleet from the source that takes the target with at least the
+// given chance of winning, if the source has enough ships for it
+fn ships_needed(view: &Game, source: PlanetId, target: PlanetId, available: usize, confidence: f64) -> Option<usize> {
+    // Skip working out the odds when the target's garrison alone outguns
+    // the whole fleet
+    let (from, to) = (view.planet(source)?, view.planet(target)?);
+    if available * to.attack_strength(from.effective_strength()) < to.ships * to.defence_strength() {
+        return None;
+    }
+    let wins = |count| view.attack_odds(source, target, count)
+        .is_some_and(|odds| odds.win_probability >= confidence);
+    if available == 0 || !wins(available) {
+        return None;
+    }
+    let (mut low, mut high) = (1, available);
+    while low < high {
+        let mid = (low + high) / 2;
+        if wins(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Some(low)
+}
+
+// Chance of winning the bots want before attacking neutral planets...
+const NEUTRAL_CONFIDENCE: f64 = 0.8;
+// ... and enemy planets
+const ENEMY_CONFIDENCE: f64 = 0.9;
+
+// Grabs the nearest weak neutral planets first, then goes after the weakest
+// enemy planet it can overwhelm
+pub struct GreedyBot;
+
+impl PlayerController for GreedyBot {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand> {
+        // Planets our fleets are already on their way to
+        let mut targeted: HashSet<PlanetId> = view.fleets()
+            .filter(|f| f.owner == me)
+            .map(|f| f.destination)
+            .collect();
+        let mut commands = Vec::new();
+        for (source, planet) in view.planets().filter(|(_, p)| p.owner == Some(me)) {
+            let mut neutrals: Vec<PlanetId> = view.planets()
+                .filter(|(id, p)| p.owner.is_none() && !targeted.contains(id))
+                .map(|(id, _)| id)
+                .collect();
+            neutrals.sort_by_key(|&id| view.planet(id).map(|p| (distance(planet, p), p.ships)));
+            let mut enemies: Vec<PlanetId> = view.planets()
+                .filter(|(id, p)| p.owner.is_some_and(|owner| !view.allied(me, owner)) && !targeted.contains(id))
+                .map(|(id, _)| id)
+                .collect();
+            enemies.sort_by_key(|&id| view.planet(id).map(|p| (p.ships, distance(planet, p))));
+            let choice = neutrals.iter().map(|&id| (id, NEUTRAL_CONFIDENCE))
+                .chain(enemies.iter().map(|&id| (id, ENEMY_CONFIDENCE)))
+                .find_map(|(target, confidence)| {
+                    let count = ships_needed(view, source, target, planet.ships, confidence)?;
+                    view.check_fleet(me, source, target, count, None).ok()?;
+                    Some((target, count))
+                });
+            if let Some((target, count)) = choice {
+                targeted.insert(target);
+                commands.push(order(source, target, count));
+            }
+        }
+        commands
+    }
+}
+
+// Ships DefensiveBot keeps home for every enemy ship on its way to a planet
+const THREAT_GARRISON: f64 = 1.5;
+// Garrison DefensiveBot keeps even when nothing is coming
+const MIN_GARRISON: usize = 10;
+// DefensiveBot only attacks when it can spare this many times the ships
+// needed to win
+const SURPLUS_FACTOR: usize = 3;
+
+// Keeps garrisons in proportion to the fleets heading for its planets and
+// sends spare ships to threatened planets. Counter-attacks only with a
+// large surplus, preferring planets that attacked it.
+pub struct DefensiveBot;
+
+impl PlayerController for DefensiveBot {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand> {
+        let incoming = |id: PlanetId| view.fleets().filter(move |f| !view.allied(me, f.owner) && f.destination == id);
+        // Ships each planet can spare, and ships it is short of
+        let mut spare = Vec::new();
+        let mut short = Vec::new();
+        for (id, planet) in view.planets().filter(|(_, p)| p.owner == Some(me)) {
+            let threat: usize = incoming(id).map(|f| f.ships).sum();
+            let garrison = ((threat as f64 * THREAT_GARRISON).ceil() as usize).max(MIN_GARRISON);
+            if planet.ships > garrison {
+                spare.push((id, planet.ships - garrison));
+            } else if planet.ships < garrison && threat > 0 {
+                short.push((id, garrison - planet.ships));
+            }
+        }
+        let mut commands = Vec::new();
+
+        // Reinforcements only help if they land before the first attacker;
+        // fleets launched this turn land after those already in flight, so
+        // landing in the same turn is too late
+        short.sort_by_key(|&(_, missing)| std::cmp::Reverse(missing));
+        for (target, mut missing) in short {
+            let deadline = incoming(target).map(|f| f.turns_to_arrival).min().unwrap_or(0);
+            let target_planet = match view.planet(target) {
+                Some(planet) => planet,
+                None => continue,
+            };
+            spare.sort_by_key(|&(id, _)| view.planet(id).map(|p| distance(p, target_planet)));
+            for (source, available) in spare.iter_mut() {
+                if missing == 0 {
+                    break;
+                }
+                let count = missing.min(*available);
+                let in_time = view.planet(*source).is_some_and(|p| distance(p, target_planet) < deadline);
+                if count > 0 && in_time && view.check_fleet(me, *source, target, count, None).is_ok() {
+                    commands.push(order(*source, target, count));
+                    *available -= count;
+                    missing -= count;
+                }
+            }
+        }
+
+        // Hit back at whoever attacked us first, then the nearest planets
+        let attackers: HashSet<PlayerId> = view.fleets()
+            .filter(|f| !view.allied(me, f.owner) && view.planet(f.destination).is_some_and(|p| p.owner == Some(me)))
+            .map(|f| f.owner)
+            .collect();
+        for (source, available) in spare {
+            let source_planet = match view.planet(source) {
+                Some(planet) => planet,
+                None => continue,
+            };
+            let mut targets: Vec<PlanetId> = view.planets()
+                .filter(|(_, p)| !p.owner.is_some_and(|owner| view.allied(me, owner)))
+                .map(|(id, _)| id)
+                .collect();
+            targets.sort_by_key(|&id| view.planet(id).map(|p| {
+                let attacked_us = p.owner.is_some_and(|owner| attackers.contains(&owner));
+                (!attacked_us, distance(source_planet, p))
+            }));
+            let attack = targets.into_iter().find_map(|target| {
+                let count = ships_needed(view, source, target, available / SURPLUS_FACTOR, ENEMY_CONFIDENCE)?;
+                view.check_fleet(me, source, target, count, None).ok()?;
+                Some((target, count))
+            });
+            if let Some((target, count)) = attack {
+                commands.push(order(source, target, count));
+            }
+        }
+        commands
+    }
+}
+
+// How many turns LookaheadBot plays out...
+const LOOKAHEAD_TURNS: usize = 4;
+// ... how many times for each candidate order...
+const LOOKAHEAD_ROLLOUTS: usize = 6;
+// ... and how many targets it considers for each of its planets
+const LOOKAHEAD_TARGETS: usize = 4;
+
+// Tries single orders from each of its planets against the nearest other
+// planets, plays a few turns out on copies of the game for each, and keeps
+// the orders that score better than sitting still, by the game's own
+// scoring
+pub struct LookaheadBot {
+    rng: StdRng,
+}
+
+impl LookaheadBot {
+    pub fn new(seed: u64) -> LookaheadBot {
+        LookaheadBot {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // Average score after playing the orders out
+    fn evaluate(&mut self, view: &Game, me: PlayerId, orders: &[SendShipsCommand]) -> f64 {
+        let total: f64 = (0..LOOKAHEAD_ROLLOUTS)
+            .map(|_| {
+                let future = view.simulate(me, orders, LOOKAHEAD_TURNS, &mut self.rng);
+                future.scoring().score(&future, me)
+            })
+            .sum();
+        total / LOOKAHEAD_ROLLOUTS as f64
+    }
+}
+
+impl PlayerController for LookaheadBot {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand> {
+        let mut candidates = Vec::new();
+        for (source, planet) in view.planets().filter(|(_, p)| p.owner == Some(me) && p.ships > 0) {
+            let mut targets: Vec<(PlanetId, usize)> = view.planets()
+                .filter(|(id, p)| *id != source && !p.owner.is_some_and(|owner| view.allied(me, owner)))
+                .map(|(id, p)| (id, distance(planet, p)))
+                .collect();
+            targets.sort_by_key(|&(_, d)| d);
+            for (target, _) in targets.into_iter().take(LOOKAHEAD_TARGETS) {
+                // The simulations tell whether half or all of the ships will do
+                for count in [planet.ships / 2, planet.ships] {
+                    if count > 0 && view.check_fleet(me, source, target, count, None).is_ok() {
+                        candidates.push(order(source, target, count));
+                    }
+                }
+            }
+        }
+        let baseline = self.evaluate(view, me, &[]);
+        let mut scored: Vec<(f64, SendShipsCommand)> = candidates.into_iter()
+            .map(|candidate| (self.evaluate(view, me, std::slice::from_ref(&candidate)), candidate))
+            .filter(|(score, _)| *score > baseline)
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        // Best order for each planet
+        let mut used = HashSet::new();
+        scored.into_iter()
+            .filter(|(_, command)| used.insert(command.source_planet_id))
+            .map(|(_, command)| command)
+            .collect()
+    }
+}
@@ -1,11 +1,56 @@
-use std::io::{self, Write};
+use std::collections::{BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
 use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-use crate::game::{CouldNotSend, Game, Message, Planet, PlanetId, PlayerId, distance};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use terminal_size::{terminal_size, Height, Width};
+
+use crate::commentary::{MessageFormatter, PlainFormatter};
+use crate::controller::PlayerController;
+use crate::scoring::Scoring;
+use crate::replay::{CouldNotReplay, Replay};
+use crate::report::{graph_symbol, GameSummary, WarReport};
+use crate::series::Series;
+use crate::game::{CombatModel, CouldNotAlly, CouldNotCancel, CouldNotSend, CouldNotSurrender, Diplomacy, Game, Message, Player, SendShipsCommand, Planet, PlanetId, PlayerId, Terrain, VictoryCondition, distance,
+                  ASTEROID_DEFENCE_BONUS, NEBULA_ATTACK_PENALTY};
 
-const PLANET_NAMES : &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
 impl fmt::Display for CouldNotSend {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CouldNotSend::HomeProtected { until_turn } =>
+                write!(f, "That planet is under home protection until the end of turn {}", until_turn),
+            CouldNotSend::SamePlanet => write!(f, "Ships cannot be sent to the planet they are on"),
+            _ => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl fmt::Display for CouldNotSurrender {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for CouldNotCancel {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for CouldNotAlly {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl fmt::Display for CouldNotReplay {
     fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{:?}", self)
     }
@@ -15,60 +60,283 @@ pub struct Cli {
     game: Game,
     current_player_id: PlayerId,
     players_to_make_moves: Vec<PlayerId>,
+    preferences: Preferences,
+    // Where `set` saves the preferences
+    preferences_file: Option<String>,
+    formatter: Box<dyn MessageFormatter>,
+    // The game as each player left it when they last ended their turn
+    snapshots: HashMap<PlayerId, Game>,
+    // Report being collected, and the file it is written to at the end
+    war_report: Option<(WarReport, String)>,
+    // Shown once the game is won
+    summary: GameSummary,
+    // Replay being recorded, and the file it is written to at the end
+    replay: Option<(Replay, String)>,
+    clocks: Option<Clocks>,
+    // Decides the battles
+    rng: StdRng,
+    // Players whose moves are not entered at the prompt
+    controllers: HashMap<PlayerId, Box<dyn PlayerController>>,
+    // Messages meant for one player, shown when their turn comes
+    inbox: HashMap<PlayerId, Vec<String>>,
+    // Orders the current player has undone, most recent last
+    redo: Vec<SendShipsCommand>,
+}
+
+// Chess-style clocks: time spent on a player's moves comes out of their
+// bank, and the increment is added back when they end their turn
+struct Clocks {
+    remaining: HashMap<PlayerId, Duration>,
+    increment: Duration,
+    // When the current player started making their moves
+    started: Instant,
 }
 
-fn print_game_map(game: &Game) {
+pub const DEFAULT_IDLE_WARNING : usize = 50;
+
+// How the game talks to the player; none of these change the rules
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Preferences {
+    // Show the plan and ask before ending a turn
+    pub confirm_turn_end: bool,
+    // Show the odds and ask for confirmation before attacking
+    pub predict_battles: bool,
+    // Warn about planets with at least this many ships doing nothing
+    pub idle_warning: Option<usize>,
+    // Keep giving eliminated players a turn to watch the game
+    pub spectate: bool,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            confirm_turn_end: true,
+            predict_battles: true,
+            idle_warning: Some(DEFAULT_IDLE_WARNING),
+            spectate: true,
+        }
+    }
+}
+
+impl Preferences {
+    pub fn load(path: &str) -> io::Result<Preferences> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut out, self)?;
+        out.flush()
+    }
+
+    fn print(&self) {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        println!("confirm {}", on_off(self.confirm_turn_end));
+        println!("predict {}", on_off(self.predict_battles));
+        match self.idle_warning {
+            Some(threshold) => println!("idle-warning {}", threshold),
+            None => println!("idle-warning off"),
+        }
+        println!("spectate {}", on_off(self.spectate));
+    }
+
+    fn set(&mut self, name: &str, value: &str) -> Result<(), String> {
+        let on_off = || match value {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            _ => Err(format!("{} needs on or off", name)),
+        };
+        match name {
+            "confirm" => self.confirm_turn_end = on_off()?,
+            "predict" => self.predict_battles = on_off()?,
+            "spectate" => self.spectate = on_off()?,
+            "idle-warning" => self.idle_warning = match value {
+                "off" => None,
+                _ => Some(value.parse().map_err(|_| "idle-warning needs a number of ships or off".to_string())?),
+            },
+            _ => return Err(format!("No preference called {}", name)),
+        }
+        Ok(())
+    }
+}
+
+// A game saved in the middle of a turn, along with whose move it is
+#[derive(Serialize, Deserialize)]
+struct SavedGame {
+    game: Game,
+    current_player_id: PlayerId,
+    players_to_make_moves: Vec<PlayerId>,
+}
+
+// Used when stdout is not a terminal, e.g. when piping output to a file
+const DEFAULT_TERMINAL_SIZE : (usize, usize) = (80, 24);
+
+fn terminal_dimensions() -> (usize, usize) {
+    terminal_size()
+        .map(|(Width(w), Height(h))| (w as usize, h as usize))
+        .unwrap_or(DEFAULT_TERMINAL_SIZE)
+}
+
+fn wait_for_more() {
+    print!("-- more (press enter) --");
+    io::stdout().flush().expect("Could not flush stdout");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Could not get input");
+}
+
+fn player_name(game: &Game, id: PlayerId) -> String {
+    game.player(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into())
+}
+
+// One line with every player still in the game, marked as in the
+// ownership graph. Through the fog of war the viewer only counts the
+// planets and fleets they can see.
+fn print_legend(game: &Game, viewer: Option<PlayerId>) {
+    let mut totals: Vec<(PlayerId, usize, usize)> = game.standings().into_iter().map(|(id, stats)| match viewer {
+        Some(viewer) if game.rules().fog.is_some() => {
+            let planets: Vec<&Planet> = game.planets()
+                .filter(|(planet, p)| p.owner == Some(id) && game.visible(viewer, *planet))
+                .map(|(_, p)| p)
+                .collect();
+            let in_flight: usize = game.fleets().filter(|f| f.owner == id && game.fleet_visible(viewer, f)).map(|f| f.ships).sum();
+            (id, planets.len(), planets.iter().map(|p| p.ships).sum::<usize>() + in_flight)
+        }
+        _ => (id, stats.planets, stats.ships),
+    }).collect();
+    // Ordered as standings orders them, by what the viewer can see
+    totals.sort_by(|(ia, pa, sa), (ib, pb, sb)| pb.cmp(pa).then(sb.cmp(sa)).then(ia.cmp(ib)));
+    let entries: Vec<String> = totals.into_iter().map(|(id, planets, ships)| {
+        let index = game.players().position(|(player, _)| player == id).unwrap_or(0);
+        let name = player_name(game, id);
+        format!("{} {}: {} planets, {} ships", graph_symbol(index), name, planets, ships)
+    }).collect();
+    println!("{}", entries.join(" | "));
+}
+
+// Planets out of the player's range are shown in lower case; without a
+// player, every planet is shown
+fn print_game_map(game: &Game, player_id: Option<PlayerId>) {
     let (w, h) = game.size();
+    let (term_w, _) = terminal_dimensions();
+    // Big games run out of letters and use longer names
+    let name_width = game.planets().map(|(_, p)| p.name.len()).max().unwrap_or(1);
+    // Every cell takes a separator and a planet name, plus the closing separator
+    let map_width = (name_width + 1) * w + 1;
+    if map_width > term_w {
+        println!("The map is {} columns wide but the terminal only has {}.", map_width, term_w);
+        println!("Widen the terminal to see the map, or use `i` and `d` to inspect planets.");
+        return;
+    }
     for y in 0..h {
         for x in 0..w {
-            print!("│{}",
+            print!("│{: <width$}",
             game.planets()
-                   .zip(PLANET_NAMES.chars())
-                   .filter(|((_, p), _)| p.pos == (x, y))
-                   .take(1).last()
-                   .map(|(_p, c)| c.to_string())
-                   .unwrap_or(" ".to_string())
+                   .find(|(id, p)| p.pos == (x, y) && player_id.is_none_or(|player| game.visible(player, *id)))
+                   .map(|(id, p)| match player_id {
+                       Some(player) if !game.in_range(player, id) => p.name.to_ascii_lowercase(),
+                       _ => p.name.clone(),
+                   })
+                   .unwrap_or_default(),
+            width = name_width
             );
         }
         println!("│")
     }
 }
 
-fn print_game_info(game: &Game, planet_names: &[String]) {
-    println!(" Planet | Ships  | Power  | Prod   | Owner");
-    let print_planet = |(_, planet): (PlanetId, &Planet)| {
-        println!(
-            " {: ^6} | {: >6} | {: >6} | {: >6} | {}",
+fn format_strength(planet: &Planet) -> String {
+    if planet.veterancy > 0 {
+        format!("{}+{}", planet.strength, planet.veterancy)
+    } else {
+        planet.strength.to_string()
+    }
+}
+
+fn print_game_info(game: &Game, player_id: PlayerId, planet_names: &[String]) {
+    let (term_w, _) = terminal_dimensions();
+    // The full table needs 41 columns before the owner name; fall back to
+    // narrow columns on small terminals.
+    let col = if term_w < 41 + 10 { 4 } else { 6 };
+    let show_fuel = game.rules().fuel.is_some();
+    let show_terrain = game.rules().terrain;
+    let mut header = if col < 6 {
+        format!(" {: ^6} | {: <4} | {: <4} | {: <4} | ", "Planet", "Shps", "Pow", "Prod")
+    } else {
+        format!(" {: ^6} | {: <6} | {: <6} | {: <6} | ", "Planet", "Ships", "Power", "Prod")
+    };
+    if show_fuel {
+        header += &format!("{: <col$} | ", "Fuel", col = col);
+    }
+    if show_terrain {
+        header += &format!("{: <8} | ", "Terrain");
+    }
+    header += "Owner";
+    let format_planet = |planet: &Planet| {
+        let mut row = format!(
+            " {: ^6} | {: >col$} | {: >col$} | {: >col$} | ",
             planet.name,
             planet.ships,
-            planet.strength,
+            format_strength(planet),
             planet.production,
-            planet.owner.map(|i| game.player(i).map(|p| p.name.clone()).unwrap_or("?".into())).unwrap_or("-".into())
-        )
+            col = col
+        );
+        if show_fuel {
+            row += &format!("{: >col$} | ", planet.fuel, col = col);
+        }
+        if show_terrain {
+            row += &format!("{: <8} | ", match planet.terrain {
+                Terrain::Open => "",
+                Terrain::Nebula => "nebula",
+                Terrain::AsteroidBelt => "asteroid",
+            });
+        }
+        row += &planet.owner.map(|i| game.player(i).map(|p| p.name.clone()).unwrap_or("?".into())).unwrap_or("-".into());
+        row
     };
-    if planet_names.is_empty() {
-        game.planets().for_each(print_planet)
+    let rows: Vec<String> = if planet_names.is_empty() {
+        game.planets()
+            .filter(|(id, _)| game.visible(player_id, *id))
+            .map(|(_, p)| format_planet(p))
+            .collect()
     } else {
-        let planets = planet_names.iter().filter_map(|tok| {
-            let planet_index = game.get_planet_id(tok).map_err(|e| println!("Planet {}: {}, skipping", tok, e)).ok();
-            planet_index.and_then(|i| game.planet(i).ok().map(|p| (i, p)))
-        });
-        planets.for_each(print_planet)
+        planet_names.iter().filter_map(|tok| {
+            let planet_index = game.get_planet_id(tok)
+                .and_then(|id| if game.visible(player_id, id) { Ok(id) } else { Err("hidden by the fog of war".to_string()) })
+                .map_err(|e| println!("Planet {}: {}, skipping", tok, e)).ok();
+            planet_index.and_then(|i| game.planet(i)).map(format_planet)
+        }).collect()
+    };
+    print_paged(&header, &rows);
+}
+
+// Prints a table a screenful at a time, repeating the header on every page
+fn print_paged(header: &str, rows: &[String]) {
+    let (_, term_h) = terminal_dimensions();
+    // Leave room for the header, the pager prompt and the command prompt
+    let page_size = term_h.saturating_sub(3).max(1);
+    for (page, chunk) in rows.chunks(page_size).enumerate() {
+        if page > 0 {
+            wait_for_more();
+        }
+        println!("{}", header);
+        chunk.iter().for_each(|row| println!("{}", row));
     }
 }
 
-fn show_distances(game: &Game) {
-    show_distances_for(game, game.planets().map(|p| p.0).collect())
+fn show_distances(game: &Game, player_id: PlayerId) {
+    show_distances_for(game, game.planets().map(|p| p.0).filter(|id| game.visible(player_id, *id)).collect())
 }
 
 fn show_distances_for(game: &Game, planet_ids: Vec<PlanetId>) {
-    let planets: Vec<&Planet> = planet_ids.iter().filter_map(|id| game.planet(*id).ok()).collect();
-    print!("\\|");
+    let planets: Vec<&Planet> = planet_ids.iter().filter_map(|id| game.planet(*id)).collect();
+    let name_width = planets.iter().map(|p| p.name.len()).max().unwrap_or(1);
+    print!("{: <width$}|", "\\", width = name_width);
     for p in planets.iter() {
         print!("{: ^3}|", p.name);
     }
     for p1 in planets.iter() {
-        print!("\n{}|", p1.name);
+        print!("\n{: <width$}|", p1.name, width = name_width);
         for p2 in planets.iter() {
             let d = distance(p1, p2);
             if d != 0 {
@@ -81,39 +349,562 @@ fn show_distances_for(game: &Game, planet_ids: Vec<PlanetId>) {
     print!("\n\n");
 }
 
+fn print_rules(game: &Game) {
+    let rules = game.rules();
+    println!("Combat: {}", match rules.combat {
+        CombatModel::AlternatingRolls =>
+            "each round the defender shoots first, then the attacker; every shot hits with a chance equal to the shooter's strength in percent and destroys one enemy ship",
+        CombatModel::NormalisedRolls =>
+            "each round the defender shoots first, then the attacker; every shot that hits destroys one enemy ship, and the chance to hit grows with strength along a curve: 3% at strength 0, 50% at 50 and 97% at 100",
+    });
+    println!("Production: at the end of every turn, before fleets launch, each owned planet builds its production in ships; neutral planets build nothing");
+    println!("Distance: half the straight-line distance between two planets, rounded up, in turns");
+    println!("Fleets: fight with the strength of the planet they were launched from");
+    if rules.terrain {
+        println!("Terrain: attackers fight at {} less strength over nebula planets, defenders at {} more strength on asteroid belt planets",
+                 NEBULA_ATTACK_PENALTY, ASTEROID_DEFENCE_BONUS);
+    }
+    if let Some(veterancy) = rules.veterancy {
+        println!("Veterans: fleets that capture a planet gain {} strength, up to {} in total; the bonus stays with the garrison and with fleets launched from it, and strength never exceeds 100",
+                 veterancy.bonus, veterancy.max_bonus);
+    }
+    match rules.max_range {
+        Some(range) => println!("Range: fleets can only be sent to planets at most {} turns away; out-of-range planets are shown in lower case on the map", range),
+        None => println!("Range: unlimited"),
+    }
+    if let Some(fuel) = rules.fuel {
+        println!("Fuel: owned planets gain {} fuel per turn; fleets travelling more than {} turns burn one fuel from their source planet per extra turn; captured planets lose their fuel",
+                 fuel.production, fuel.free_range);
+    }
+    println!("Home planets: {} ships, strength {}, production {}", rules.home_ships, rules.home_strength, rules.home_production);
+    if let Some(range) = rules.fog {
+        println!("Fog of war: you only see planets within {} turns of your own planets, and only your own fleets", range);
+    }
+    if let Some(protection) = rules.protection {
+        println!("Protection: until the end of turn {}, no fleet may be sent against a player's planets within {} turns of their home planet",
+                 protection.turns, protection.radius);
+    }
+    let teams: BTreeSet<usize> = game.players().filter_map(|(_, p)| p.team).collect();
+    if !teams.is_empty() {
+        let lineup: Vec<String> = teams.iter().map(|&team| {
+            let names: Vec<&str> = game.team(team).iter().filter_map(|&id| game.player(id)).map(|p| p.name.as_str()).collect();
+            format!("team {}: {}", team, names.join(", "))
+        }).collect();
+        println!("Teams: {}; allies reinforce each other's planets instead of fighting, share what they see and win together",
+                 lineup.join("; "));
+    }
+    println!("Diplomacy: an alliance is made at the end of the turn once both players have offered it; allies fight and win as a team until one of them breaks the alliance");
+    match rules.victory {
+        VictoryCondition::LastPlayerStanding =>
+            println!("Victory: the last player with planets or fleets left wins"),
+        VictoryCondition::KingOfTheHill { turns } => {
+            let hill = game.hill().and_then(|id| game.planet(id)).map(|p| p.name.clone()).unwrap_or("?".into());
+            println!("Victory: hold planet {} for {} consecutive turns, or be the last player left", hill, turns)
+        }
+        VictoryCondition::Economic { target } =>
+            println!("Victory: be the first to build {} ships in total, or be the last player left", target),
+    }
+    if let Some(limit) = rules.turn_limit {
+        println!("Turn limit: if nobody has won by the end of turn {}, the highest score wins ({})",
+                 limit, game.scoring().describe());
+    }
+}
+
+// Players are listed from the highest score down. Through the fog of war
+// only the player's own side is scored, and everyone else comes last.
+fn print_score(game: &Game, player_id: PlayerId) {
+    let target = match game.rules().victory {
+        VictoryCondition::Economic { target } => Some(target),
+        _ => None,
+    };
+    let header = format!(" {: <10} | {: >7} | {: >8} | {: >6} | {: >6} | {: >9}", "Player", "Score", "Built", "Prod", "Lost", "Destroyed");
+    let header = match target {
+        Some(target) => format!("{} | Progress to {}", header, target),
+        None => header,
+    };
+    let hidden_rows = game.players().filter(|&(id, _)| !game.shares_vision(player_id, id)).map(|(_, player)| {
+        format!(" {: <10} | {: >7} | {: >8} | {: >6} | {: >6} | {: >9}", player.name, "?", "?", "?", "?", "?")
+    });
+    let seen = game.scores().into_iter().filter(|&(id, _)| game.shares_vision(player_id, id));
+    let rows: Vec<String> = seen.map(|(id, score)| {
+        let name = player_name(game, id);
+        let stats = game.stats(id);
+        let row = format!(" {: <10} | {: >7.0} | {: >8} | {: >6} | {: >6} | {: >9}",
+                          name, score, stats.ships_built, stats.production, stats.ships_lost, stats.ships_destroyed);
+        match target {
+            Some(target) => format!("{} | {}%", row, (stats.ships_built * 100 / target.max(1)).min(100)),
+            None => row,
+        }
+    }).chain(hidden_rows).collect();
+    print_paged(&header, &rows);
+    for (a, b) in game.alliances() {
+        println!("{} and {} are allies", player_name(game, a), player_name(game, b));
+    }
+    for (from, to) in game.alliance_proposals() {
+        println!("{} has offered {} an alliance", player_name(game, from), player_name(game, to));
+    }
+    for (vassal, lord) in game.vassals() {
+        println!("{} has surrendered to {}", player_name(game, vassal), player_name(game, lord));
+    }
+}
+
+// Through the fog of war only the planets the player can see are counted
+fn print_galaxy(game: &Game, player_id: PlayerId) {
+    let header = format!(" {: <10} | {: >7} | {: >6} | {: >5} | {: >8} | Centre", "Owner", "Planets", "Ships", "Prod", "Avg prod");
+    let owners = game.players()
+        .map(|(id, player)| (Some(id), player.name.clone()))
+        .chain(Some((None, "Neutral".to_string())));
+    let rows: Vec<String> = owners.map(|(owner, name)| {
+        let empire = game.empire_seen_by(player_id, owner);
+        let average = if empire.planets > 0 {
+            format!("{:.1}", empire.production as f32 / empire.planets as f32)
+        } else {
+            "-".to_string()
+        };
+        let centre = empire.centre
+            .map(|(x, y)| format!("({:.1}, {:.1})", x, y))
+            .unwrap_or("-".to_string());
+        format!(" {: <10} | {: >7} | {: >6} | {: >5} | {: >8} | {}", name, empire.planets, empire.ships, empire.production, average, centre)
+    }).collect();
+    print_paged(&header, &rows);
+    match game.largest_neutral_prize(player_id).and_then(|id| game.planet(id)) {
+        Some(planet) => println!("Largest neutral prize: planet {} (production {}, strength {})",
+                                 planet.name, planet.production, planet.strength),
+        None if game.rules().fog.is_some() => println!("No neutral planets in sight."),
+        None => println!("No neutral planets left."),
+    }
+}
+
+// Fleets are listed by time to arrival
+fn print_fleets(game: &Game, player_id: PlayerId) {
+    let planet_name = |id| game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
+    let mut fleets: Vec<_> = game.fleets().filter(|f| game.fleet_visible(player_id, f)).collect();
+    if fleets.is_empty() {
+        println!("No fleets in transit.");
+        return;
+    }
+    fleets.sort_by_key(|f| f.turns_to_arrival);
+    let header = format!(" {: <10} | {: >6} | {: <4} | {: <4} | {: >5} | Name", "Owner", "Ships", "From", "To", "Turns");
+    let rows: Vec<String> = fleets.into_iter().map(|fleet| {
+        format!(" {: <10} | {: >6} | {: <4} | {: <4} | {: >5} | {}", player_name(game, fleet.owner), fleet.ships, planet_name(fleet.source),
+                planet_name(fleet.destination), fleet.turns_to_arrival, fleet.name.as_deref().unwrap_or("-"))
+    }).collect();
+    print_paged(&header, &rows);
+}
+
+fn print_diff(before: &Game, now: &Game, player_id: PlayerId) {
+    let owner_name = |owner: Option<PlayerId>| owner.map(|id| player_name(now, id)).unwrap_or("neutral".into());
+    let planet_name = |id| now.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
+    let mut changed = false;
+    for ((_, old), (_, new)) in before.planets().zip(now.planets()).filter(|((id, _), _)| now.visible(player_id, *id)) {
+        if old.owner != new.owner {
+            println!("Planet {}: {} -> {}, {} ships", new.name, owner_name(old.owner), owner_name(new.owner), new.ships);
+            changed = true;
+        } else if old.ships != new.ships {
+            println!("Planet {}: {} -> {} ships ({:+})", new.name, old.ships, new.ships, new.ships as i64 - old.ships as i64);
+            changed = true;
+        }
+    }
+    for (id, player) in now.players() {
+        // Only planets the player can see now are counted, before and after
+        let count = |game: &Game| game.planets().filter(|(planet, p)| p.owner == Some(id) && now.visible(player_id, *planet)).count();
+        let (old, new) = (count(before), count(now));
+        if old != new {
+            println!("{} now has {} planets ({:+})", player.name, new, new as i64 - old as i64);
+            changed = true;
+        }
+    }
+    for fleet in now.fleets().filter(|f| now.fleet_visible(player_id, f) && !before.fleets().any(|old| old.id == f.id)) {
+        println!("New fleet: {} ships of {} from {} to {}, arriving in {} turns",
+                 fleet.ships, player_name(now, fleet.owner), planet_name(fleet.source), planet_name(fleet.destination), fleet.turns_to_arrival);
+        changed = true;
+    }
+    if !changed {
+        println!("Nothing has changed.");
+    }
+}
+
+pub fn print_timings(game: &Game) {
+    let timings = game.timings();
+    let turns = timings.turns.max(1) as u32;
+    println!("Engine timings over {} turns (total / per turn):", timings.turns);
+    let phases = [
+        ("production", timings.production),
+        ("launch", timings.launch),
+        ("movement", timings.movement),
+        ("combat", timings.combat),
+    ];
+    for (name, total) in phases.iter() {
+        println!(" {: <10} | {: >12?} | {: >12?}", name, total, *total / turns);
+    }
+}
+
+// Steps through a recorded game a turn at a time, with nothing hidden
+pub fn play_replay(replay: &Replay, formatter: &mut dyn MessageFormatter) -> Result<(), CouldNotReplay> {
+    let mut game = replay.start.clone();
+    let mut summary = GameSummary::new();
+    // Replays of loaded games start part way through
+    let last_turn = replay.start.turn() + replay.turns.len().saturating_sub(1);
+    for turn in replay.turns.iter() {
+        print_legend(&game, None);
+        print_game_map(&game, None);
+        print!("-- turn {} of {}, press enter for the next turn or q to stop --", game.turn(), last_turn);
+        io::stdout().flush().expect("Could not flush stdout");
+        let mut input = String::new();
+        io::stdin().read_line(&mut input).expect("Could not get input");
+        if input.trim().eq_ignore_ascii_case("q") {
+            return Ok(())
+        }
+        println!("\n----- Turn {} ended ------", game.turn());
+        let events = turn.apply(&mut game)?;
+        summary.record_turn(&game, &events);
+        // Defenders' accounts repeat the battles already told by the attackers
+        for event in events.iter().filter(|event| !matches!(event.message, Message::PlanetAttacked(_))) {
+            println!("{}", formatter.format(&game, &event.message));
+        }
+    }
+    print_legend(&game, None);
+    print_game_map(&game, None);
+    summary.print(&game);
+    Ok(())
+}
+
+// Neutral production counts towards a player's start within this many turns
+const BALANCE_RADIUS: usize = 3;
+
+// How fair the starting positions are, so that a lopsided map can be
+// thrown away before the game starts
+pub fn print_balance(game: &Game) {
+    let turns = |value: Option<usize>| value.map(|t| t.to_string()).unwrap_or("-".to_string());
+    println!(" {: <10} | {: >15} | {: >14} | {: >16}", "Player", "Nearest neutral", format!("Prod within {}", BALANCE_RADIUS), "Nearest opponent");
+    let balance = game.balance(BALANCE_RADIUS);
+    for start in balance.iter() {
+        let name = player_name(game, start.player);
+        println!(" {: <10} | {: >15} | {: >14} | {: >16}", name, turns(start.nearest_neutral), start.nearby_production, turns(start.nearest_opponent));
+    }
+    let production = balance.iter().map(|start| start.nearby_production);
+    if let (Some(least), Some(most)) = (production.clone().min(), production.max()) {
+        println!("Nearby neutral production ranges from {} to {}.", least, most);
+    }
+}
+
+pub fn print_series(series: &Series, players: &[Player]) {
+    let score: Vec<String> = players.iter().zip(series.wins()).map(|(player, wins)| format!("{} {}", player.name, wins)).collect();
+    let draws = match series.draws() {
+        0 => String::new(),
+        draws => format!(", {} drawn", draws),
+    };
+    println!("Series score after game {}: {}{}", series.played(), score.join(", "), draws);
+    if series.is_over() {
+        match series.winner() {
+            Some(winner) => println!("{} wins the series!", players[winner].name),
+            None => println!("The series ends level."),
+        }
+    }
+}
+
+fn print_plan(game: &Game, player_id: PlayerId) {
+    let planet_name = |id| game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
+    let mut empty = true;
+    for (index, command) in game.queued_commands(player_id).enumerate() {
+        let turns = match (game.planet(command.source_planet_id), game.planet(command.destination_planet_id)) {
+            (Some(src), Some(dest)) => distance(src, dest).to_string(),
+            _ => "?".to_string(),
+        };
+        let name = command.name.as_ref().map(|n| format!(" as \"{}\"", n)).unwrap_or_default();
+        println!("{}. Send {} ships from {} to {}{}, arriving in {} turns",
+                 index + 1, command.count, planet_name(command.source_planet_id), planet_name(command.destination_planet_id), name, turns);
+        empty = false;
+    }
+    for (other, action) in game.queued_diplomacy(player_id) {
+        let name = player_name(game, other);
+        match action {
+            Diplomacy::ProposeAlliance => println!("Offer {} an alliance", name),
+            Diplomacy::BreakAlliance => println!("Break with {}", name),
+        }
+        empty = false;
+    }
+    if let Some(winner) = game.queued_surrender(player_id) {
+        println!("Surrender to {}", player_name(game, winner));
+        empty = false;
+    }
+    if empty {
+        println!("No orders queued this turn.");
+    }
+}
+
+fn print_preview(game: &Game, player_id: PlayerId) {
+    let planet_name = |id| game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
+    let arrivals = game.preview(player_id);
+    if arrivals.is_empty() {
+        println!("No fleets arrive when this turn ends.");
+    }
+    for arrival in arrivals {
+        match arrival.odds {
+            None => println!("{} ships of {} from {} reinforce {}",
+                             arrival.ships, player_name(game, arrival.owner), planet_name(arrival.source), planet_name(arrival.destination)),
+            Some(odds) => println!("{} ships of {} from {} attack {} against about {} defenders: {:.0}% chance to capture, {:.1} ships expected to survive a victory",
+                                   arrival.ships, player_name(game, arrival.owner), planet_name(arrival.source), planet_name(arrival.destination),
+                                   odds.defenders, odds.win_probability * 100.0, odds.expected_survivors),
+        }
+    }
+    if game.rules().fog.is_some() {
+        println!("Other players' orders for this turn and fleets heading into the fog are not included.");
+    } else {
+        println!("Other players' orders for this turn are not included.");
+    }
+}
+
+fn print_threats(game: &Game, player_id: PlayerId) {
+    let planet_name = |id| game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
+    let threats = game.threats(player_id);
+    if threats.is_empty() {
+        println!("No enemy fleets are heading for your planets.");
+        return;
+    }
+    let header = format!(" {: <6} | {: <10} | {: >6} | {: >3} | {: >9} | Capture chance", "Planet", "Enemy", "Ships", "ETA", "Defenders");
+    let rows: Vec<String> = threats.into_iter().map(|threat| {
+        format!(" {: <6} | {: <10} | {: >6} | {: >3} | {: >9} | {:.0}%", planet_name(threat.destination), player_name(game, threat.owner), threat.ships,
+                threat.turns_to_arrival, threat.odds.defenders, threat.odds.win_probability * 100.0)
+    }).collect();
+    print_paged(&header, &rows);
+}
+
+fn print_idle_warning(game: &Game, player_id: PlayerId, threshold: usize) {
+    for planet in game.idle_planets(player_id, threshold).into_iter().filter_map(|id| game.planet(id)) {
+        println!("Planet {} has {} ships doing nothing.", planet.name, planet.ships);
+    }
+}
+
+fn confirm(question: &str) -> bool {
+    print!("{} [Y/n] ", question);
+    io::stdout().flush().expect("Could not flush stdout");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input).expect("Could not get input");
+    !input.trim().eq_ignore_ascii_case("n")
+}
+
 impl Cli {
     pub fn new(game: Game) -> Cli {
-        let player_ids : Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
-        let first_player_id = player_ids.iter().min().expect("Game should have at least one player");
-        let result = Cli {
+        let mut player_ids : Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+        // Players move in ascending order, popping from the back
+        player_ids.sort_by(|a, b| b.cmp(a));
+        let first_player_id = player_ids.pop().expect("Game should have at least one player");
+        Cli {
             game,
-            current_player_id: *first_player_id,
+            current_player_id: first_player_id,
             players_to_make_moves: player_ids,
+            preferences: Preferences::default(),
+            preferences_file: None,
+            formatter: Box::new(PlainFormatter),
+            snapshots: HashMap::new(),
+            war_report: None,
+            summary: GameSummary::new(),
+            replay: None,
+            clocks: None,
+            rng: StdRng::from_entropy(),
+            controllers: HashMap::new(),
+            inbox: HashMap::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn write_war_report(&mut self, report: WarReport, path: String) {
+        self.war_report = Some((report, path));
+    }
+
+    // Record the game from here on, and write the replay to `path` once it
+    // is over
+    pub fn record_replay(&mut self, path: String) {
+        self.replay = Some((Replay::new(&self.game), path));
+    }
+
+    // The replay being recorded, if any
+    pub fn replay(&self) -> Option<&Replay> {
+        self.replay.as_ref().map(|(replay, _)| replay)
+    }
+
+    // Let the controller make the player's moves instead of asking for them
+    pub fn set_controller(&mut self, player_id: PlayerId, controller: Box<dyn PlayerController>) {
+        self.controllers.insert(player_id, controller);
+    }
+
+    // Ranks players in the standings and decides the game at the turn limit
+    pub fn set_scoring(&mut self, scoring: Box<dyn Scoring>) {
+        self.game.set_scoring(Rc::from(scoring));
+    }
+
+    // Seed the battles, so that the same orders always give the same results
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    pub fn set_formatter(&mut self, formatter: Box<dyn MessageFormatter>) {
+        self.formatter = formatter;
+    }
+
+    // Changes made with `set` are saved to `file`
+    pub fn set_preferences(&mut self, preferences: Preferences, file: Option<String>) {
+        self.preferences = preferences;
+        self.preferences_file = file;
+    }
+
+    // Give every player `bank` to make all their moves, plus `increment`
+    // each turn; players who run out forfeit
+    pub fn set_clock(&mut self, bank: Duration, increment: Duration) {
+        self.clocks = Some(Clocks {
+            remaining: self.game.players().map(|(id, _)| (id, bank)).collect(),
+            increment,
+            started: Instant::now(),
+        });
+    }
+
+    fn time_left(&self) -> Option<Duration> {
+        self.clocks.as_ref().map(|clocks| {
+            let bank = clocks.remaining.get(&self.current_player_id).cloned().unwrap_or_default();
+            bank.saturating_sub(clocks.started.elapsed())
+        })
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    fn save(&self, path: &str) -> io::Result<()> {
+        let saved = SavedGame {
+            game: self.game.clone(),
+            current_player_id: self.current_player_id,
+            players_to_make_moves: self.players_to_make_moves.clone(),
         };
-        result
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, &saved)?;
+        out.flush()
+    }
+
+    fn load(&mut self, path: &str) -> io::Result<()> {
+        let saved: SavedGame = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        // Saved games do not keep the scoring they were played with
+        let scoring = self.game.scoring().clone();
+        self.game = saved.game;
+        self.game.set_scoring(scoring);
+        self.current_player_id = saved.current_player_id;
+        self.players_to_make_moves = saved.players_to_make_moves;
+        // Snapshots and news belong to the game that was being played
+        self.snapshots.clear();
+        self.inbox.clear();
+        self.redo.clear();
+        self.summary = GameSummary::new();
+        // The replay starts over from the loaded game
+        if let Some((replay, _)) = &mut self.replay {
+            *replay = Replay::new(&self.game);
+        }
+        if let Some(clocks) = &mut self.clocks {
+            clocks.started = Instant::now();
+        }
+        Ok(())
     }
 
     pub fn play(&mut self) {
         while self.game.get_winner().is_none() {
-            self.do_turn();
+            if self.controllers.contains_key(&self.current_player_id) {
+                self.controlled_turn();
+            } else {
+                self.do_turn();
+            }
+        }
+        self.summary.print(&self.game);
+        if let Some((report, path)) = &self.war_report {
+            match report.write(&self.game, path) {
+                Ok(()) => println!("War report written to {}", path),
+                Err(e) => println!("Could not write war report to {}: {}", path, e),
+            }
+        }
+        if let Some((replay, path)) = &self.replay {
+            match replay.write(path) {
+                Ok(()) => println!("Replay written to {}", path),
+                Err(e) => println!("Could not write replay to {}: {}", path, e),
+            }
         }
     }
 
+    fn controlled_turn(&mut self) {
+        let player_id = self.current_player_id;
+        // Bots have no use for news
+        self.inbox.remove(&player_id);
+        let commands = match self.controllers.get_mut(&player_id) {
+            Some(controller) => controller.take_turn(&self.game, player_id),
+            None => return,
+        };
+        let name = self.game.player(player_id).unwrap().name.clone();
+        for command in commands {
+            let result = self.game.queue_fleet(player_id, command.source_planet_id, command.destination_planet_id, command.count, command.name);
+            if let Err(e) = result {
+                println!("Player {} gave an invalid order: {}", name, e);
+            }
+        }
+        println!("Player {} has made their moves.", name);
+        self.next_player();
+    }
+
     fn do_turn(&mut self) {
         let mut input = String::new();
-        print_game_map(&self.game);
+        let clock = match self.time_left() {
+            _ if self.spectating() => " (spectating)".to_string(),
+            Some(left) => format!(" ({}:{:02} left)", left.as_secs() / 60, left.as_secs() % 60),
+            None => String::new(),
+        };
+        if let Some(messages) = self.inbox.remove(&self.current_player_id) {
+            println!("News for {}:", self.game.player(self.current_player_id).unwrap().name);
+            for message in messages {
+                println!("  {}", message);
+            }
+        }
+        print_legend(&self.game, Some(self.current_player_id));
+        print_game_map(&self.game, Some(self.current_player_id));
         print!("
 s A B n - send n ships from A to B
+s A B n as \"Name\" - send a named fleet
+eta A B - show how long a fleet from A takes to reach B
+odds A B n - show the chances of n ships from A capturing B
 d - show distances between all planets
 d A B C … - show distance for trips between A, B, C…
 i - info on planets
 i A B … - info on specific planets
+g - galaxy overview per owner
+f - list the fleets in transit that you can see
+t - list enemy fleets heading for your planets and how their attacks will likely go
+diff - show what changed since you last ended your turn
+preview - show the fleets arriving when this turn ends and how their battles will likely go
+rules - show the rules of this game
+score - show standings, ships built, production and ships lost and destroyed per player
+surrender P - give all your planets and fleets to player P
+ally P - offer player P an alliance, or accept theirs
+break P - end your alliance with player P, or withdraw your offer
+timing - show time spent resolving turns
+set - show preferences
+set NAME VALUE - change a preference: confirm on|off, predict on|off, idle-warning SHIPS|off, spectate on|off
+save FILE - save the game to FILE
+load FILE - load a game saved with save
+c - list your orders for this turn
+c N - cancel order number N
+u - undo your last order
+r - redo the last order you undid
 n - finish turn
-Player {}: ", self.game.player(self.current_player_id).unwrap().name);
-        io::stdout().flush();
+Turn {}, player {}{}: ", self.game.turn(), self.game.player(self.current_player_id).unwrap().name, clock);
+        io::stdout().flush().expect("Could not flush stdout");
 
         match io::stdin().read_line(&mut input) {
             Ok(count) if count > 0 => {
+                if self.time_left() == Some(Duration::ZERO) && !self.spectating() {
+                    let name = self.game.player(self.current_player_id).unwrap().name.clone();
+                    println!("Player {} has run out of time and forfeits!", name);
+                    self.game.forfeit(self.current_player_id);
+                    if let Some((replay, _)) = &mut self.replay {
+                        replay.record_forfeit(self.current_player_id);
+                    }
+                    self.next_player();
+                    return;
+                }
                 let cmd = self.do_command(input.split_whitespace().map(|s| s.to_string()).collect());
                 cmd.unwrap_or_else(|e| println!("{}", e));
             },
@@ -122,35 +913,59 @@ Player {}: ", self.game.player(self.current_player_id).unwrap().name);
     }
 
     fn reset_moves(&mut self) {
-        self.players_to_make_moves = self.game.remaining_players().drain().collect();
-        self.players_to_make_moves.sort_by(|a, b| b.cmp(a));
+        let remaining = self.game.remaining_players();
+        // Eliminated players may stay on to watch, unless they are bots
+        let spectate = self.preferences.spectate;
+        self.players_to_make_moves = self.game.players()
+            .map(|(id, _)| id)
+            .filter(|id| remaining.contains(id) || (spectate && !self.controllers.contains_key(id)))
+            .collect();
+        // Players move in ascending order, popping from the back
+        self.players_to_make_moves.reverse();
+    }
+
+    fn spectating(&self) -> bool {
+        !self.game.remaining_players().contains(&self.current_player_id)
     }
 
     fn next_player(&mut self) {
+        self.redo.clear();
         match self.players_to_make_moves.pop() {
-            Some(p) => { self.current_player_id = p; },
+            Some(p) => {
+                self.current_player_id = p;
+                if let Some(clocks) = &mut self.clocks {
+                    clocks.started = Instant::now();
+                }
+            },
             None => self.complete_turn(),
         }
     }
 
     fn complete_turn(&mut self) {
-        println!("\n\n\n----- Turn ended ------");
-        let messages = self.game.end_turn();
-        let player_name = |id| self.game.player(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
-        let planet_name = |id| self.game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into());
-        for message in messages {
-            match message {
-                Message::AttackFailed(fleet) => {
-                    println!("Fleet from player {} failed to take planet {}.", player_name(fleet.owner), planet_name(fleet.destination));
-                }
-                Message::AttackSucceeded(fleet) => {
-                    println!("Fleet from player {} took over planet {}!", player_name(fleet.owner), planet_name(fleet.destination));
-                }
-                Message::ReinforcementsArrived(fleet) => {
-                    println!("Reinforcements of {} ships have arrived at planet {}.", fleet.ships, planet_name(fleet.destination));
-                }
-                Message::PlayerEliminated(player) => {
-                    println!("Player {} was eliminated!", player.name);
+        println!("\n\n\n----- Turn {} ended ------", self.game.turn());
+        // Each turn's battles get a seed of their own, so replays can
+        // decide them the same way
+        let seed = self.rng.gen();
+        if let Some((replay, _)) = &mut self.replay {
+            replay.record_turn(&self.game, seed);
+        }
+        let events = self.game.end_turn(&mut StdRng::seed_from_u64(seed));
+        self.summary.record_turn(&self.game, &events);
+        if let Some((report, _)) = &mut self.war_report {
+            report.record_turn(&self.game, &events);
+        }
+        for event in events {
+            let text = self.formatter.format(&self.game, &event.message);
+            match event.message.recipient() {
+                Some(player) => self.inbox.entry(player).or_default().push(format!("Turn {}: {}", event.turn, text)),
+                None => println!("{}", text),
+            }
+        }
+        if let VictoryCondition::KingOfTheHill { turns } = self.game.rules().victory {
+            if let Some(hill) = self.game.hill().and_then(|id| self.game.planet(id)) {
+                match hill.owner {
+                    Some(owner) => println!("Planet {} has been held by {} for {}/{} turns.", hill.name, player_name(&self.game, owner), hill.held_for, turns),
+                    None => println!("Planet {} is still neutral.", hill.name),
                 }
             }
         }
@@ -158,41 +973,236 @@ Player {}: ", self.game.player(self.current_player_id).unwrap().name);
         self.next_player();
     }
 
-    fn do_command(&mut self, tokens: Vec<String>) -> Result<(), String> {
-        if tokens.len() < 1 {
+    // Carries out one command as typed at the prompt, split into words
+    pub fn do_command(&mut self, tokens: Vec<String>) -> Result<(), String> {
+        if tokens.is_empty() {
             return Err("No command provided".to_string())
         }
+        if self.spectating() && matches!(tokens[0].as_str(), "s" | "surrender" | "ally" | "break") {
+            return Err("You have been eliminated and can only watch".to_string());
+        }
         match tokens[0].as_str() {
+            "n" if self.spectating() => {
+                self.next_player();
+                Ok(())
+            },
             "n" => {
+                if let Some(threshold) = self.preferences.idle_warning {
+                    print_idle_warning(&self.game, self.current_player_id, threshold);
+                }
+                if self.preferences.confirm_turn_end {
+                    print_plan(&self.game, self.current_player_id);
+                    if !confirm("End turn?") {
+                        return Ok(());
+                    }
+                }
+                self.snapshots.insert(self.current_player_id, self.game.clone());
+                if let Some(clocks) = &mut self.clocks {
+                    if let Some(bank) = clocks.remaining.get_mut(&self.current_player_id) {
+                        *bank = bank.saturating_sub(clocks.started.elapsed()) + clocks.increment;
+                    }
+                }
                 self.next_player();
-                return Ok(());
+                Ok(())
+            },
+            "preview" => {
+                print_preview(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "diff" => {
+                match self.snapshots.get(&self.current_player_id) {
+                    Some(before) => print_diff(before, &self.game, self.current_player_id),
+                    None => println!("You have not finished a turn yet."),
+                }
+                Ok(())
+            },
+            "set" => {
+                match tokens.len() {
+                    1 => self.preferences.print(),
+                    3 => {
+                        self.preferences.set(&tokens[1], &tokens[2])?;
+                        if let Some(path) = &self.preferences_file {
+                            self.preferences.save(path).map_err(|e| format!("Could not save preferences to {}: {}", path, e))?;
+                        }
+                    }
+                    _ => return Err("Need a preference and a value".to_string()),
+                }
+                Ok(())
+            },
+            "save" => {
+                let path = tokens.get(1).ok_or("Need a file name")?;
+                self.save(path).map_err(|e| format!("Could not save to {}: {}", path, e))?;
+                println!("Game saved to {}", path);
+                Ok(())
+            },
+            "load" => {
+                let path = tokens.get(1).ok_or("Need a file name")?;
+                self.load(path).map_err(|e| format!("Could not load {}: {}", path, e))?;
+                println!("Game loaded from {}", path);
+                Ok(())
+            },
+            "timing" => {
+                print_timings(&self.game);
+                Ok(())
             },
             "i" => {
-                print_game_info(&self.game, &tokens[1..]);
-                return Ok(());
+                print_game_info(&self.game, self.current_player_id, &tokens[1..]);
+                Ok(())
+            },
+            "surrender" => {
+                if tokens.len() != 2 {
+                    return Err("Need the name of the player to surrender to".to_string());
+                }
+                let to = self.game.players()
+                    .find(|(_, p)| p.name == tokens[1])
+                    .map(|(id, _)| id)
+                    .ok_or("No such player".to_string())?;
+                self.game.surrender(self.current_player_id, to).map_err(|e| e.to_string())?;
+                self.next_player();
+                Ok(())
+            },
+            "ally" | "break" => {
+                if tokens.len() != 2 {
+                    return Err("Need the name of a player".to_string());
+                }
+                let other = self.game.players()
+                    .find(|(_, p)| p.name == tokens[1])
+                    .map(|(id, _)| id)
+                    .ok_or("No such player".to_string())?;
+                let result = if tokens[0] == "ally" {
+                    self.game.propose_alliance(self.current_player_id, other)
+                } else {
+                    self.game.break_alliance(self.current_player_id, other)
+                };
+                result.map_err(|e| e.to_string())
+            },
+            "g" => {
+                print_galaxy(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "f" => {
+                print_fleets(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "c" if tokens.len() == 1 => {
+                print_plan(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "c" => {
+                let number = tokens[1].parse::<usize>().ok().filter(|&n| n > 0)
+                    .ok_or("Need the number of the order to cancel".to_string())?;
+                self.game.cancel_queued(self.current_player_id, number - 1).map(|_| ()).map_err(|e| e.to_string())
+            },
+            "t" => {
+                print_threats(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "score" => {
+                print_score(&self.game, self.current_player_id);
+                Ok(())
+            },
+            "rules" => {
+                print_rules(&self.game);
+                Ok(())
             },
             "s" => {
+                let name = match tokens.get(4).map(|s| s.as_str()) {
+                    None if tokens.len() == 4 => None,
+                    Some("as") if tokens.len() > 5 => Some(tokens[5..].join(" ").trim_matches('"').to_string()),
+                    _ => return Err("Need a source and destination planet and a number of ships, optionally followed by as \"Name\"".to_string()),
+                };
+                let src = self.game.get_planet_id(&tokens[1])?;
+                let dest = self.game.get_planet_id(&tokens[2])?;
+                let count = tokens[3].parse::<usize>()
+                                   .map_err(|_| "Invalid number of ships".to_string())?;
+                self.game.check_fleet(self.current_player_id, src, dest, count, name.as_deref()).map_err(|e| e.to_string())?;
+                let hostile = self.game.planet(dest)
+                    .map(|p| !p.owner.is_some_and(|owner| self.game.allied(self.current_player_id, owner)))
+                    .unwrap_or(false);
+                // The garrison of a planet in the fog is not known
+                let visible = self.game.visible(self.current_player_id, dest);
+                if self.preferences.predict_battles && hostile && visible {
+                    if let Some(odds) = self.game.attack_odds(src, dest, count) {
+                        println!("{} ships against about {} defenders: {:.0}% chance to capture, {:.1} ships expected to survive a victory.",
+                                 odds.attackers, odds.defenders, odds.win_probability * 100.0, odds.expected_survivors);
+                        if !confirm("Send the fleet?") {
+                            return Ok(());
+                        }
+                    }
+                }
+                self.game.queue_fleet(self.current_player_id, src, dest, count, name).map_err(|e| e.to_string())?;
+                self.redo.clear();
+                Ok(())
+            },
+            "eta" => {
+                if tokens.len() != 3 {
+                    return Err("Need a source and destination planet".to_string());
+                }
+                let src = self.game.get_planet_id(&tokens[1])?;
+                let dest = self.game.get_planet_id(&tokens[2])?;
+                if !self.game.visible(self.current_player_id, src) || !self.game.visible(self.current_player_id, dest) {
+                    return Err("That planet is hidden by the fog of war".to_string());
+                }
+                let (from, to) = match (self.game.planet(src), self.game.planet(dest)) {
+                    (Some(from), Some(to)) => (from, to),
+                    _ => return Err("No such planet".to_string()),
+                };
+                // Fleets launch and move in the same end of turn
+                let turns = distance(from, to);
+                println!("A fleet sent from {} this turn reaches {} in {} turns, when turn {} ends.",
+                         from.name, to.name, turns, self.game.turn() + turns.saturating_sub(1));
+                if self.game.rules().max_range.is_some_and(|range| turns > range) {
+                    println!("That is out of range.");
+                }
+                Ok(())
+            },
+            "odds" => {
                 if tokens.len() != 4 {
                     return Err("Need a source and destination planet and a number of ships".to_string());
                 }
                 let src = self.game.get_planet_id(&tokens[1])?;
                 let dest = self.game.get_planet_id(&tokens[2])?;
-                let count = usize::from_str_radix(tokens[3].as_str(), 10)
+                let count = tokens[3].parse::<usize>()
                                    .map_err(|_| "Invalid number of ships".to_string())?;
-                return self.game.queue_fleet(*&self.current_player_id, src, dest, count).map_err(|e| e.to_string());
+                // The strength of the fleet comes from its source
+                if !self.game.visible(self.current_player_id, src) || !self.game.visible(self.current_player_id, dest) {
+                    return Err("That planet is hidden by the fog of war".to_string());
+                }
+                let odds = self.game.attack_odds(src, dest, count).ok_or("No such planet".to_string())?;
+                println!("{} ships against about {} defenders: {:.0}% chance to capture, {:.1} ships expected to survive a victory.",
+                         odds.attackers, odds.defenders, odds.win_probability * 100.0, odds.expected_survivors);
+                Ok(())
+            },
+            "u" => {
+                let last = self.game.queued_commands(self.current_player_id).count().checked_sub(1)
+                    .ok_or("Nothing to undo".to_string())?;
+                let command = self.game.cancel_queued(self.current_player_id, last).map_err(|e| e.to_string())?;
+                self.redo.push(command);
+                Ok(())
+            },
+            "r" => {
+                let command = self.redo.pop().ok_or("Nothing to redo".to_string())?;
+                let result = self.game.queue_fleet(self.current_player_id, command.source_planet_id, command.destination_planet_id, command.count, command.name.clone());
+                if let Err(e) = result {
+                    self.redo.push(command);
+                    return Err(e.to_string());
+                }
+                Ok(())
             },
             "d" => {
                 let chosen : Vec<PlanetId> = tokens.iter().skip(1).filter_map(|tok| {
-                    self.game.get_planet_id(tok).map_err(|e| println!("Planet {}: {}, skipping", tok, e)).ok()
+                    self.game.get_planet_id(tok)
+                        .and_then(|id| if self.game.visible(self.current_player_id, id) { Ok(id) } else { Err("hidden by the fog of war".to_string()) })
+                        .map_err(|e| println!("Planet {}: {}, skipping", tok, e)).ok()
                 }).collect();
                 if chosen.is_empty() {
-                    show_distances(&self.game);
+                    show_distances(&self.game, self.current_player_id);
                 } else {
                     show_distances_for(&self.game, chosen);
                 }
-                return Ok(());
+                Ok(())
             }
-            _ => return Err("No command".to_string())
+            _ => Err("No command".to_string())
         }
     }
 
@@ -0,0 +1,246 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+
+use crate::game::{BattleReport, Fleet, Game, Message, Planet, PlanetId, Player, PlayerId};
+
+// Turns engine messages into the text shown to players
+pub trait MessageFormatter {
+    fn format(&mut self, game: &Game, message: &Message) -> String;
+}
+
+fn player_name(game: &Game, id: PlayerId) -> String {
+    game.player(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into())
+}
+
+fn planet_name(game: &Game, id: PlanetId) -> String {
+    game.planet(id).map(|p| p.name.clone()).unwrap_or("<unknown>".into())
+}
+
+fn fleet_label(fleet: &Fleet) -> String {
+    match &fleet.name {
+        Some(name) => format!("Fleet \"{}\"", name),
+        None => "Fleet".to_string(),
+    }
+}
+
+fn score_list(scores: &[(Player, f64)]) -> String {
+    let scores: Vec<String> = scores.iter().map(|(player, score)| format!("{} {:.0}", player.name, score)).collect();
+    scores.join(", ")
+}
+
+fn casualties(battle: &BattleReport) -> String {
+    format!(" Casualties after {} rounds: {} of {} attackers, {} of {} defenders.",
+            battle.rounds, battle.attacker_losses, battle.attackers, battle.defender_losses, battle.defenders)
+}
+
+pub struct PlainFormatter;
+
+impl MessageFormatter for PlainFormatter {
+    fn format(&mut self, game: &Game, message: &Message) -> String {
+        match message {
+            Message::Battle(battle) => {
+                let fleet = &battle.fleet;
+                let outcome = if battle.captured {
+                    format!("{} from player {} took over planet {}!", fleet_label(fleet), player_name(game, fleet.owner), planet_name(game, fleet.destination))
+                } else {
+                    format!("{} from player {} failed to take planet {}.", fleet_label(fleet), player_name(game, fleet.owner), planet_name(game, fleet.destination))
+                };
+                format!("{}{}", outcome, casualties(battle))
+            }
+            Message::ReinforcementsArrived(fleet) => {
+                let name = fleet.name.as_ref().map(|n| format!(" (\"{}\")", n)).unwrap_or_default();
+                format!("Reinforcements of {} ships{} have arrived at planet {}.", fleet.ships, name, planet_name(game, fleet.destination))
+            }
+            Message::PlayerEliminated(elimination) => {
+                let by = elimination.by.map(|id| format!(" by {}", player_name(game, id))).unwrap_or_default();
+                let allies = if elimination.allies_remain { " Their allies fight on." } else { "" };
+                format!("Player {} was eliminated{} on turn {}! They built {} ships and lost {} in battle.{}",
+                        elimination.player.name, by, elimination.turn, elimination.ships_built, elimination.ships_lost, allies)
+            }
+            Message::PlayerSurrendered(loser, winner) => {
+                format!("Player {} surrendered to {}, handing over all planets and fleets.", loser.name, winner.name)
+            }
+            Message::AllianceProposed(from, to) => {
+                format!("Player {} offers an alliance to {}.", from.name, to.name)
+            }
+            Message::AllianceFormed(a, b) => {
+                format!("Players {} and {} are now allies.", a.name, b.name)
+            }
+            Message::AllianceBroken(from, to) => {
+                format!("Player {} broke their alliance with {}!", from.name, to.name)
+            }
+            Message::PlanetAttacked(defence) => {
+                format!("Your planet {} was attacked by {} ({}, {} ships remain).", planet_name(game, defence.planet), player_name(game, defence.attacker),
+                        if defence.held { "held" } else { "lost" }, defence.ships_left)
+            }
+            Message::FinalScores(scores) => {
+                format!("The turn limit has been reached. Final scores: {}.", score_list(scores))
+            }
+        }
+    }
+}
+
+// Placeholders: {player}, {other}, {fleet}, {ships} and {planet}
+const ATTACK_FAILED: &[&str] = &[
+    "{player}'s {fleet} crashes against {planet} and is wiped out!",
+    "{planet} holds firm; not one of {player}'s ships makes it home.",
+    "The guns of {planet} turn {player}'s {fleet} into drifting scrap.",
+    "{player} throws ships at {planet}, and {planet} throws them back.",
+];
+const ATTACK_SUCCEEDED: &[&str] = &[
+    "{player}'s {fleet} storms {planet}; {ships} battered ships raise a new flag!",
+    "{planet} falls! {player} now rules its skies.",
+    "After a fierce fight {player} claims {planet} with {ships} ships to spare.",
+    "The defenders of {planet} break and run before {player}'s {fleet}.",
+];
+const REINFORCEMENTS: &[&str] = &[
+    "{ships} fresh ships of {player} drop into orbit over {planet}.",
+    "{planet} cheers as {player}'s {fleet} of {ships} arrives.",
+    "{player} bolsters {planet} with another {ships} ships.",
+];
+const ELIMINATED: &[&str] = &[
+    "The empire of {player} is no more! {ships} ships built, and all for nothing.",
+    "{player}'s last light goes out among the stars after {ships} ships built.",
+    "History will remember {player}, builder of {ships} ships. Briefly.",
+];
+const ELIMINATED_BY: &[&str] = &[
+    "{other} snuffs out the empire of {player}!",
+    "{player} falls to {other}; {ships} ships built, and all for nothing.",
+    "{other} writes the final chapter of {player}'s story.",
+];
+const SURRENDERED: &[&str] = &[
+    "{player} bends the knee and hands everything to {other}.",
+    "White flags everywhere: {player} surrenders to {other}!",
+];
+const ALLIANCE_PROPOSED: &[&str] = &[
+    "{player} extends a hand of friendship to {other}.",
+    "Envoys of {player} arrive before {other} with an offer of alliance.",
+];
+const ALLIANCE_FORMED: &[&str] = &[
+    "{player} and {other} swear to fight side by side.",
+    "A new alliance: {player} and {other} join forces!",
+];
+const ALLIANCE_BROKEN: &[&str] = &[
+    "{player} tears up the treaty with {other}!",
+    "Betrayal! {player} turns on former ally {other}.",
+];
+const FINAL_SCORES: &[&str] = &[
+    "Time is up! {player} finishes on top.",
+    "The final bell rings, and {player} leads the galaxy.",
+];
+const DEFENCE_HELD: &[&str] = &[
+    "{planet} beats off {other}'s attack; {ships} defenders still stand.",
+    "{other} strikes at {planet}, but the garrison holds with {ships} ships.",
+];
+const DEFENCE_LOST: &[&str] = &[
+    "{planet} has fallen to {other}, who holds it with {ships} ships.",
+    "The garrison of {planet} is overwhelmed by {other}'s {ships} ships.",
+];
+
+fn describe_planet(planet: &Planet) -> String {
+    if planet.strength >= 70 {
+        format!("the fortress world of {}", planet.name)
+    } else if planet.production >= 13 {
+        format!("the forge world of {}", planet.name)
+    } else {
+        format!("planet {}", planet.name)
+    }
+}
+
+fn describe_fleet(fleet: &Fleet) -> String {
+    match &fleet.name {
+        Some(name) => format!("\"{}\"", name),
+        None if fleet.ships >= 50 => "armada".to_string(),
+        None if fleet.ships >= 10 => "fleet".to_string(),
+        None => "squadron".to_string(),
+    }
+}
+
+// Narrates messages with phrases picked from a phrase bank; the same seed
+// gives the same commentary for the same messages.
+pub struct Commentator {
+    rng: StdRng,
+}
+
+impl Commentator {
+    pub fn new(seed: u64) -> Commentator {
+        Commentator { rng: StdRng::seed_from_u64(seed) }
+    }
+
+    fn pick(&mut self, phrases: &[&str]) -> String {
+        phrases.choose(&mut self.rng).expect("Phrase banks are not empty").to_string()
+    }
+}
+
+impl MessageFormatter for Commentator {
+    fn format(&mut self, game: &Game, message: &Message) -> String {
+        let fleet_planet = |fleet: &Fleet| game.planet(fleet.destination)
+            .map(describe_planet)
+            .unwrap_or("<unknown>".into());
+        let mut suffix = String::new();
+        let (phrase, player, other, fleet, ships, planet) = match message {
+            Message::Battle(battle) => {
+                suffix = casualties(battle);
+                let fleet = &battle.fleet;
+                (
+                    self.pick(if battle.captured { ATTACK_SUCCEEDED } else { ATTACK_FAILED }), player_name(game, fleet.owner), String::new(),
+                    describe_fleet(fleet), fleet.ships, fleet_planet(fleet),
+                )
+            }
+            Message::ReinforcementsArrived(fleet) => (
+                self.pick(REINFORCEMENTS), player_name(game, fleet.owner), String::new(),
+                describe_fleet(fleet), fleet.ships, fleet_planet(fleet),
+            ),
+            Message::PlayerEliminated(elimination) => {
+                if elimination.allies_remain {
+                    suffix = " Their allies fight on.".to_string();
+                }
+                match elimination.by {
+                    Some(by) => (
+                        self.pick(ELIMINATED_BY), elimination.player.name.clone(), player_name(game, by),
+                        String::new(), elimination.ships_built, String::new(),
+                    ),
+                    None => (
+                        self.pick(ELIMINATED), elimination.player.name.clone(), String::new(),
+                        String::new(), elimination.ships_built, String::new(),
+                    ),
+                }
+            }
+            Message::PlayerSurrendered(loser, winner) => (
+                self.pick(SURRENDERED), loser.name.clone(), winner.name.clone(), String::new(), 0, String::new(),
+            ),
+            Message::AllianceProposed(from, to) => (
+                self.pick(ALLIANCE_PROPOSED), from.name.clone(), to.name.clone(), String::new(), 0, String::new(),
+            ),
+            Message::AllianceFormed(a, b) => (
+                self.pick(ALLIANCE_FORMED), a.name.clone(), b.name.clone(), String::new(), 0, String::new(),
+            ),
+            Message::AllianceBroken(from, to) => (
+                self.pick(ALLIANCE_BROKEN), from.name.clone(), to.name.clone(), String::new(), 0, String::new(),
+            ),
+            Message::PlanetAttacked(defence) => (
+                self.pick(if defence.held { DEFENCE_HELD } else { DEFENCE_LOST }), player_name(game, defence.defender),
+                player_name(game, defence.attacker), String::new(), defence.ships_left,
+                game.planet(defence.planet).map(describe_planet).unwrap_or("<unknown>".into()),
+            ),
+            Message::FinalScores(scores) => {
+                suffix = format!(" Final scores: {}.", score_list(scores));
+                let leader = scores.first().map(|(player, _)| player.name.clone()).unwrap_or_default();
+                (self.pick(FINAL_SCORES), leader, String::new(), String::new(), 0, String::new())
+            }
+        };
+        let text = phrase
+            .replace("{player}", &player)
+            .replace("{other}", &other)
+            .replace("{fleet}", &fleet)
+            .replace("{ships}", &ships.to_string())
+            .replace("{planet}", &planet) + &suffix;
+        // Phrases may start with a planet description
+        let mut chars = text.chars();
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => text,
+        }
+    }
+}
@@ -0,0 +1,7 @@
+use crate::game::{Game, PlayerId, SendShipsCommand};
+
+// Makes the moves for a player the Cli does not ask for input. Orders the
+// game refuses are reported and skipped.
+pub trait PlayerController {
+    fn take_turn(&mut self, view: &Game, me: PlayerId) -> Vec<SendShipsCommand>;
+}
@@ -1,31 +1,84 @@
-use std::collections::HashSet;
-use rand::{Rng, thread_rng};
+use std::collections::{BTreeSet, HashMap};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use rand::Rng;
 use rand::seq::SliceRandom;
 use rand_distr::Binomial;
+use serde::{Deserialize, Serialize};
+
+use crate::observer::{GameObserver, Observers};
+use crate::scoring::{Scoring, SharedScoring};
 
 type Pos = (usize, usize);
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PlayerId(usize);
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct PlanetId(usize);
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct FleetId(usize);
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Player {
-    pub name: String
+    pub name: String,
+    // Players on the same team are allies; None plays alone
+    pub team: Option<usize>,
 }
-#[derive(Clone)]
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum Winner {
+    Player(PlayerId),
+    Team(usize),
+    // Players left standing together through alliances made in the game
+    Alliance(Vec<PlayerId>),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Diplomacy {
+    ProposeAlliance,
+    BreakAlliance,
+}
+
+fn same_team(players: &[Player], a: PlayerId, b: PlayerId) -> bool {
+    a == b || players[a.0].team.is_some() && players[a.0].team == players[b.0].team
+}
+
+fn allied(players: &[Player], alliances: &BTreeSet<(PlayerId, PlayerId)>, a: PlayerId, b: PlayerId) -> bool {
+    same_team(players, a, b) || alliances.contains(&(a.min(b), a.max(b)))
+}
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Fleet {
+    pub id: FleetId,
     pub ships: usize,
     pub strength: usize,
     pub turns_to_arrival: usize,
+    pub source: PlanetId,
     pub destination: PlanetId,
     pub owner: PlayerId,
+    pub name: Option<String>,
+    // Strength bonus earned in battle
+    pub veterancy: usize,
 }
 
-#[derive(Clone)]
+impl Fleet {
+    pub fn effective_strength(&self) -> usize {
+        (self.strength + self.veterancy).min(100)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Terrain {
+    Open,
+    // Attackers fight at NEBULA_ATTACK_PENALTY less strength
+    Nebula,
+    // Defenders fight at ASTEROID_DEFENCE_BONUS more strength
+    AsteroidBelt,
+}
+
+pub const NEBULA_ATTACK_PENALTY: usize = 10;
+pub const ASTEROID_DEFENCE_BONUS: usize = 10;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Planet {
     pub name: String,
     pub ships: usize,
@@ -33,13 +86,114 @@ pub struct Planet {
     pub production: usize,
     pub pos: Pos,
     pub owner: Option<PlayerId>,
+    // Consecutive turns the current owner has kept the planet
+    pub held_for: usize,
+    pub fuel: usize,
+    // Strength bonus of the garrison, earned by the fleets that took the
+    // planet or reinforced it
+    pub veterancy: usize,
+    pub terrain: Terrain,
+}
+
+impl Planet {
+    pub fn effective_strength(&self) -> usize {
+        (self.strength + self.veterancy).min(100)
+    }
+
+    // Strength of the garrison when the planet is attacked
+    pub fn defence_strength(&self) -> usize {
+        match self.terrain {
+            Terrain::AsteroidBelt => (self.effective_strength() + ASTEROID_DEFENCE_BONUS).min(100),
+            _ => self.effective_strength(),
+        }
+    }
+
+    // Strength of an attacking fleet fighting over this planet
+    pub fn attack_strength(&self, fleet_strength: usize) -> usize {
+        match self.terrain {
+            Terrain::Nebula => fleet_strength.saturating_sub(NEBULA_ATTACK_PENALTY),
+            _ => fleet_strength,
+        }
+    }
+}
+
+// A fleet expected to arrive when the turn ends
+#[derive(Clone)]
+pub struct ProjectedArrival {
+    pub owner: PlayerId,
+    pub ships: usize,
+    pub source: PlanetId,
+    pub destination: PlanetId,
+    // None when the fleet reinforces a planet its owner is expected to hold
+    pub odds: Option<BattleOdds>,
+}
+
+// An enemy fleet on its way to one of a player's planets
+#[derive(Clone)]
+pub struct Threat {
+    pub fleet: FleetId,
+    pub owner: PlayerId,
+    pub ships: usize,
+    pub destination: PlanetId,
+    pub turns_to_arrival: usize,
+    // Against the garrison the planet is expected to have by then, leaving
+    // other fleets out of it
+    pub odds: BattleOdds,
+}
+
+// Aggregate figures over all planets belonging to one owner
+#[derive(Clone, Debug)]
+pub struct EmpireSummary {
+    pub planets: usize,
+    pub ships: usize,
+    pub production: usize,
+    // Average planet position, None when the owner has no planets
+    pub centre: Option<(f32, f32)>,
 }
 
+// How well a player's planets are placed, for judging whether a map is fair
 #[derive(Clone)]
-struct SendShipsCommand {
-    source_planet_id: PlanetId,
-    destination_planet_id: PlanetId,
-    count: usize,
+pub struct StartBalance {
+    pub player: PlayerId,
+    // Turns to the closest neutral planet, None when there are none
+    pub nearest_neutral: Option<usize>,
+    // Production of the neutral planets within the radius asked for
+    pub nearby_production: usize,
+    // Turns to the closest planet of a player who is not an ally
+    pub nearest_opponent: Option<usize>,
+}
+
+// How a player is doing, and how they got there
+#[derive(Clone, Debug)]
+pub struct PlayerStats {
+    pub planets: usize,
+    // Garrisoned and in flight
+    pub ships: usize,
+    pub production: usize,
+    pub fleets_in_flight: usize,
+    pub ships_built: usize,
+    pub ships_lost: usize,
+    // Ships of other players and neutral garrisons shot down by the
+    // player's fleets and planets
+    pub ships_destroyed: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SendShipsCommand {
+    pub source_planet_id: PlanetId,
+    pub destination_planet_id: PlanetId,
+    pub count: usize,
+    pub name: Option<String>,
+}
+
+// Ship, fuel and turn counts are unsigned and must never wrap. Orders
+// asking for more than there is are refused when they are queued; past
+// that, anything taken away from a count goes through take_up_to, which
+// stops at zero instead of panicking or wrapping around.
+pub fn take_up_to(from: &mut usize, amount: usize) -> usize {
+    let taken = amount.min(*from);
+    *from -= taken;
+    taken
 }
 
 pub fn distance(a: &Planet, b: &Planet) -> usize {
@@ -50,14 +204,233 @@ pub fn distance(a: &Planet, b: &Planet) -> usize {
     // Divide distance by 2 since the game pace is pretty slow otherwise
     ((dx * dx + dy * dy).sqrt() * 0.5).ceil() as usize
 }
-const PLANET_NAMES : &'static str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+#[derive(Clone, Copy, Debug)]
+pub struct BattleOdds {
+    pub attackers: usize,
+    pub defenders: usize,
+    pub win_probability: f64,
+    // Surviving attackers, averaged over the battles the attacker wins
+    pub expected_survivors: f64,
+}
 
-#[derive(Clone)]
+// Exact odds for the combat in end_turn: each round the defender rolls
+// first and the attacker only rolls if it has ships left; a hit on a
+// planet without defenders captures it.
+pub fn battle_odds(combat: CombatModel, attackers: usize, attacker_strength: usize, defenders: usize, defender_strength: usize) -> BattleOdds {
+    let pa = combat.hit_chance(attacker_strength);
+    let pd = combat.hit_chance(defender_strength);
+    let leave = 1.0 - (1.0 - pa) * (1.0 - pd);
+    // win[d] and survivors[d] (weighted by the chance of winning) for a
+    // battle starting with a attackers and d defenders; only the rows for a
+    // and a - 1 attackers are kept
+    let mut win = vec![0.0; defenders + 1];
+    let mut survivors = vec![0.0; defenders + 1];
+    if leave > 0.0 {
+        for a in 1..=attackers {
+            let (win_before, survivors_before) = (win.clone(), survivors.clone());
+            for d in 0..=defenders {
+                let (mut w, mut s) = (0.0, 0.0);
+                if a > 1 {
+                    // defender hits, then the attacker hits
+                    if d == 0 {
+                        w += pd * pa;
+                        s += pd * pa * (a - 1) as f64;
+                    } else {
+                        w += pd * pa * win_before[d - 1];
+                        s += pd * pa * survivors_before[d - 1];
+                    }
+                    // defender hits, the attacker misses
+                    w += pd * (1.0 - pa) * win_before[d];
+                    s += pd * (1.0 - pa) * survivors_before[d];
+                }
+                // defender misses, the attacker hits
+                if d == 0 {
+                    w += (1.0 - pd) * pa;
+                    s += (1.0 - pd) * pa * a as f64;
+                } else {
+                    w += (1.0 - pd) * pa * win[d - 1];
+                    s += (1.0 - pd) * pa * survivors[d - 1];
+                }
+                win[d] = w / leave;
+                survivors[d] = s / leave;
+            }
+        }
+    }
+    let win_probability = win[defenders];
+    BattleOdds {
+        attackers,
+        defenders,
+        win_probability,
+        expected_survivors: if win_probability > 0.0 { survivors[defenders] / win_probability } else { 0.0 },
+    }
+}
+
+const PLANET_NAMES : &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+// A to Z, then AA to ZZ, AAA and so on, like spreadsheet columns
+fn planet_name(mut index: usize) -> String {
+    let letters: Vec<char> = PLANET_NAMES.chars().collect();
+    let mut name = Vec::new();
+    loop {
+        name.push(letters[index % letters.len()]);
+        if index < letters.len() {
+            break;
+        }
+        index = index / letters.len() - 1;
+    }
+    name.iter().rev().collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum CombatModel {
+    // Defender and attacker take turns shooting, each hitting with a
+    // probability of its strength in percent
+    AlternatingRolls,
+    // The same rounds, but strength is mapped onto the hit chance along a
+    // logistic curve, so the strongest planets never hit every time and the
+    // weakest still hit now and then
+    NormalisedRolls,
+}
+
+// Steepness of the NormalisedRolls curve: the hit chance goes from about
+// 3% at strength 0 through 50% at 50 to about 97% at 100
+const STRENGTH_CURVE_SCALE: f64 = 15.0;
+
+impl CombatModel {
+    // The chance that one shot at the given strength hits
+    pub fn hit_chance(&self, strength: usize) -> f64 {
+        match self {
+            CombatModel::AlternatingRolls => (strength as f64 / 100.0).min(1.0),
+            CombatModel::NormalisedRolls => 1.0 / (1.0 + (-(strength as f64 - 50.0) / STRENGTH_CURVE_SCALE).exp()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum VictoryCondition {
+    LastPlayerStanding,
+    // Hold the planet closest to the centre of the map for this many turns
+    KingOfTheHill { turns: usize },
+    // Be the first to build this many ships in total
+    Economic { target: usize },
+}
+
+// Fleets going further than `free_range` turns burn one unit of fuel
+// from their source planet for every extra turn of travel
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct FuelRules {
+    pub free_range: usize,
+    // Fuel gained by every owned planet each turn
+    pub production: usize,
+}
+
+// Attackers that capture a planet gain `bonus` strength, up to `max_bonus`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct VeterancyRules {
+    pub bonus: usize,
+    pub max_bonus: usize,
+}
+
+// For the first `turns` turns, planets owned by a player within `radius`
+// turns of that player's home planet cannot be attacked
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ProtectionRules {
+    pub turns: usize,
+    pub radius: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameRules {
+    pub home_ships: usize,
+    pub home_strength: usize,
+    pub home_production: usize,
+    pub combat: CombatModel,
+    pub victory: VictoryCondition,
+    // Longest trip a fleet may be sent on, in turns
+    pub max_range: Option<usize>,
+    pub fuel: Option<FuelRules>,
+    pub veterancy: Option<VeterancyRules>,
+    // Give some neutral planets a nebula or an asteroid belt
+    pub terrain: bool,
+    pub protection: Option<ProtectionRules>,
+    // Fog of war: players only see planets within this many turns of one
+    // of their own planets
+    pub fog: Option<usize>,
+    // Once this many turns have been played, the highest score wins
+    pub turn_limit: Option<usize>,
+}
+
+impl GameRules {
+    pub fn fuel_needed(&self, source: &Planet, destination: &Planet) -> usize {
+        match self.fuel {
+            Some(fuel) => distance(source, destination).saturating_sub(fuel.free_range),
+            None => 0,
+        }
+    }
+}
+
+impl Default for GameRules {
+    fn default() -> GameRules {
+        GameRules {
+            home_ships: 10,
+            home_strength: 40,
+            home_production: 10,
+            combat: CombatModel::AlternatingRolls,
+            victory: VictoryCondition::LastPlayerStanding,
+            max_range: None,
+            fuel: None,
+            veterancy: None,
+            terrain: false,
+            protection: None,
+            fog: None,
+            turn_limit: None,
+        }
+    }
+}
+
+// Time spent in each phase of end_turn, summed over the whole game
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct PhaseTimings {
+    pub turns: usize,
+    pub production: Duration,
+    pub launch: Duration,
+    pub movement: Duration,
+    pub combat: Duration,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Game {
+    _rules: GameRules,
     _planets: Vec<Planet>,
+    _planet_ids: HashMap<String, PlanetId>,
     _players: Vec<Player>,
     _fleets: Vec<Fleet>,
+    _next_fleet_id: usize,
     _queued_commands: Vec<(PlayerId, SendShipsCommand)>,
+    _hill: Option<PlanetId>,
+    // Ships built by each player over the whole game, indexed by player
+    _produced: Vec<usize>,
+    // Ships lost in combat by each player, indexed by player
+    _ships_lost: Vec<usize>,
+    // Ships destroyed in combat by each player, indexed by player
+    _ships_destroyed: Vec<usize>,
+    // The turn being played, starting at 1
+    _turn: usize,
+    // Players giving up this turn, and who they hand their empire to
+    _surrenders: Vec<(PlayerId, PlayerId)>,
+    // Players who have surrendered, each with the player they serve
+    _vassals: BTreeSet<(PlayerId, PlayerId)>,
+    // Alliances made during the game, each pair listed lowest id first
+    _alliances: BTreeSet<(PlayerId, PlayerId)>,
+    // Standing offers of an alliance, from the first player to the second
+    _proposals: BTreeSet<(PlayerId, PlayerId)>,
+    // Diplomatic moves made this turn, which take effect when it ends
+    _diplomacy: Vec<(PlayerId, PlayerId, Diplomacy)>,
+    _timings: PhaseTimings,
+    #[serde(skip)]
+    _observers: Observers,
+    #[serde(skip)]
+    _scoring: SharedScoring,
     _w: usize,
     _h: usize,
 }
@@ -67,84 +440,352 @@ pub enum CouldNotSend {
     NoSuchPlanet,
     NotYourPlanet,
     NotEnoughShips,
+    OutOfRange,
+    NotEnoughFuel,
+    FleetNameTaken,
+    SamePlanet,
+    NoShips,
+    HomeProtected { until_turn: usize },
+}
+
+#[derive(Debug)]
+pub enum CouldNotSurrender {
+    NoSuchPlayer,
+    CannotSurrenderToSelf,
+    PlayerEliminated,
+    AlreadySurrendered,
+}
+
+#[derive(Debug)]
+pub enum CouldNotCancel {
+    NoSuchOrder,
+}
+
+#[derive(Debug)]
+pub enum CouldNotAlly {
+    NoSuchPlayer,
+    CannotAllyWithSelf,
+    PlayerEliminated,
+    AlreadyAllied,
+    AlreadyProposed,
+    NotAllied,
+    SameTeam,
 }
 
 #[derive(Debug)]
 pub enum CouldNotCreateGame {
     TooManyPlanets,
+    NoNeutralPlanetForHill,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Elimination {
+    pub player: Player,
+    // Whoever dealt the final blow; None when neutral planets did
+    pub by: Option<PlayerId>,
+    pub turn: usize,
+    pub ships_built: usize,
+    pub ships_lost: usize,
+    // Whether teammates of the player are still in the game
+    pub allies_remain: bool,
 }
 
+// A fleet's attack on a planet, told from the attacker's side
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BattleReport {
+    // The attacking fleet as it left the battle; its destination is the
+    // planet fought over
+    pub fleet: Fleet,
+    // None when the planet was neutral
+    pub defender: Option<PlayerId>,
+    // Ships on each side when the battle began
+    pub attackers: usize,
+    pub defenders: usize,
+    pub attacker_losses: usize,
+    pub defender_losses: usize,
+    pub rounds: usize,
+    // Whether the attacker took the planet
+    pub captured: bool,
+}
+
+// A battle for a player's planet, as the defender saw it
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Defence {
+    pub planet: PlanetId,
+    pub defender: PlayerId,
+    pub attacker: PlayerId,
+    pub held: bool,
+    // Ships on the planet after the battle, whoever holds it
+    pub ships_left: usize,
+}
+
+#[derive(Serialize, Deserialize)]
 pub enum Message {
-    AttackFailed(Fleet),
-    AttackSucceeded(Fleet),
+    Battle(BattleReport),
     ReinforcementsArrived(Fleet),
-    PlayerEliminated(Player),
+    PlayerEliminated(Elimination),
+    // The first player handed everything they had to the second
+    PlayerSurrendered(Player, Player),
+    // The first player offers the second an alliance
+    AllianceProposed(Player, Player),
+    AllianceFormed(Player, Player),
+    // The first player ended their alliance with the second
+    AllianceBroken(Player, Player),
+    PlanetAttacked(Defence),
+    // The turn limit was reached; every player with their score, best first
+    FinalScores(Vec<(Player, f64)>),
+}
+
+// A message as it happened: in which turn, and in what order within it
+#[derive(Serialize, Deserialize)]
+pub struct Event {
+    pub turn: usize,
+    pub sequence: usize,
+    pub message: Message,
+}
+
+impl Message {
+    // The player the message is meant for, or None when it is news for
+    // everyone
+    pub fn recipient(&self) -> Option<PlayerId> {
+        match self {
+            Message::Battle(battle) => Some(battle.fleet.owner),
+            Message::ReinforcementsArrived(fleet) => Some(fleet.owner),
+            Message::PlanetAttacked(defence) => Some(defence.defender),
+            Message::PlayerEliminated(_) | Message::PlayerSurrendered(_, _) | Message::AllianceProposed(_, _)
+                | Message::AllianceFormed(_, _) | Message::AllianceBroken(_, _) | Message::FinalScores(_) => None,
+        }
+    }
 }
 
 impl Game {
-    pub fn end_turn(&mut self) -> Vec<Message> {
+    pub fn end_turn<R: Rng>(&mut self, rng: &mut R) -> Vec<Event> {
         let mut messages = Vec::new();
+        let owners_before: Vec<Option<PlayerId>> = self._planets.iter().map(|p| p.owner).collect();
+        for (loser, winner) in self._surrenders.drain(..) {
+            for planet in self._planets.iter_mut().filter(|p| p.owner == Some(loser)) {
+                planet.owner = Some(winner);
+            }
+            for fleet in self._fleets.iter_mut().filter(|f| f.owner == loser) {
+                fleet.owner = winner;
+            }
+            self._queued_commands.retain(|(player, _)| *player != loser);
+            self._diplomacy.retain(|(player, _, _)| *player != loser);
+            self._vassals.insert((loser, winner));
+            messages.push(Message::PlayerSurrendered(self._players[loser.0].clone(), self._players[winner.0].clone()));
+        }
+        for (player, other, action) in self._diplomacy.drain(..) {
+            let (from, to) = (self._players[player.0].clone(), self._players[other.0].clone());
+            match action {
+                // Offers made by both sides seal the alliance
+                Diplomacy::ProposeAlliance if self._proposals.remove(&(other, player)) => {
+                    self._alliances.insert((player.min(other), player.max(other)));
+                    messages.push(Message::AllianceFormed(from, to));
+                }
+                Diplomacy::ProposeAlliance => {
+                    self._proposals.insert((player, other));
+                    messages.push(Message::AllianceProposed(from, to));
+                }
+                Diplomacy::BreakAlliance => {
+                    self._proposals.remove(&(player, other));
+                    if self._alliances.remove(&(player.min(other), player.max(other))) {
+                        messages.push(Message::AllianceBroken(from, to));
+                    }
+                }
+            }
+        }
+        // Surrendered players have been announced already and are not
+        // reported as eliminated
         let alive_before = self.remaining_players();
-        for planet in self._planets.iter_mut().filter(|p| p.owner != None) {
-            planet.ships += planet.production;
+        let phase_start = Instant::now();
+        for planet in self._planets.iter_mut() {
+            if let Some(owner) = planet.owner {
+                planet.ships += planet.production;
+                self._produced[owner.0] += planet.production;
+                if let Some(fuel) = self._rules.fuel {
+                    planet.fuel += fuel.production;
+                }
+            }
         }
+        let launch_start = Instant::now();
+        self._timings.production += launch_start - phase_start;
         for (player, command) in self._queued_commands.drain(..) {
-            self._planets[command.source_planet_id.0].ships -= command.count;
+            let fuel = self._rules.fuel_needed(&self._planets[command.source_planet_id.0], &self._planets[command.destination_planet_id.0]);
+            let count = take_up_to(&mut self._planets[command.source_planet_id.0].ships, command.count);
+            take_up_to(&mut self._planets[command.source_planet_id.0].fuel, fuel);
+            if count == 0 {
+                continue;
+            }
             let source_planet = &self._planets[command.source_planet_id.0];
             let destination_planet = &self._planets[command.destination_planet_id.0];
             self._fleets.push(Fleet {
-                ships: command.count,
+                id: FleetId(self._next_fleet_id),
+                ships: count,
                 strength: source_planet.strength,
                 turns_to_arrival: distance(source_planet, destination_planet),
+                source: command.source_planet_id,
                 destination: command.destination_planet_id,
                 owner: player,
+                name: command.name,
+                veterancy: source_planet.veterancy,
             });
+            self._next_fleet_id += 1;
         }
+        let movement_start = Instant::now();
+        self._timings.launch += movement_start - launch_start;
+        for fleet in self._fleets.iter_mut() {
+            take_up_to(&mut fleet.turns_to_arrival, 1);
+        }
+        let combat_start = Instant::now();
+        self._timings.movement += combat_start - movement_start;
+        // The last player to take something from each player this turn
+        let mut last_blow: Vec<Option<PlayerId>> = vec![None; self._players.len()];
+        let (players, alliances) = (&self._players, &self._alliances);
+        let combat = self._rules.combat;
         for fleet in self._fleets.iter_mut() {
-            fleet.turns_to_arrival -= 1;
             if fleet.turns_to_arrival == 0 {
-                let mut dest_planet = &mut self._planets[fleet.destination.0];
-                if Some(fleet.owner) == dest_planet.owner {
+                let dest_planet = &mut self._planets[fleet.destination.0];
+                if dest_planet.owner.is_some_and(|owner| allied(players, alliances, owner, fleet.owner)) {
                     messages.push(Message::ReinforcementsArrived(fleet.clone()));
+                    // The merged garrison gets the average bonus of its ships
+                    let total = dest_planet.ships + fleet.ships;
+                    if let Some(veterancy) = (dest_planet.veterancy * dest_planet.ships + fleet.veterancy * fleet.ships).checked_div(total) {
+                        dest_planet.veterancy = veterancy;
+                    }
                     dest_planet.ships += fleet.ships
                 } else {
+                    let (attackers, defenders) = (fleet.ships, dest_planet.ships);
+                    let mut rounds = 0;
                     loop {
+                        rounds += 1;
                         // defender roll
-                        if thread_rng().gen_bool(dest_planet.strength as f64 / 100.0) {
-                            fleet.ships -= 1;
+                        if rng.gen_bool(combat.hit_chance(dest_planet.defence_strength())) {
+                            take_up_to(&mut fleet.ships, 1);
+                            self._ships_lost[fleet.owner.0] += 1;
+                            if let Some(defender) = dest_planet.owner {
+                                self._ships_destroyed[defender.0] += 1;
+                            }
                             // defender wins
-                            if fleet.ships <= 0 {
-                                messages.push(Message::AttackFailed(fleet.clone()));
+                            if fleet.ships == 0 {
+                                last_blow[fleet.owner.0] = dest_planet.owner;
+                                messages.push(Message::Battle(BattleReport {
+                                    fleet: fleet.clone(),
+                                    defender: dest_planet.owner,
+                                    attackers,
+                                    defenders,
+                                    attacker_losses: attackers,
+                                    defender_losses: defenders - dest_planet.ships,
+                                    rounds,
+                                    captured: false,
+                                }));
+                                if let Some(defender) = dest_planet.owner {
+                                    messages.push(Message::PlanetAttacked(Defence {
+                                        planet: fleet.destination,
+                                        defender,
+                                        attacker: fleet.owner,
+                                        held: true,
+                                        ships_left: dest_planet.ships,
+                                    }));
+                                }
                                 break;
                             }
                         }
                         // attacker roll
-                        if thread_rng().gen_bool(fleet.strength as f64 / 100.0) {
+                        if rng.gen_bool(combat.hit_chance(dest_planet.attack_strength(fleet.effective_strength()))) {
                             // attacker wins
-                            if dest_planet.ships <= 0 {
+                            if dest_planet.ships == 0 {
+                                if let Some(veterancy) = self._rules.veterancy {
+                                    fleet.veterancy = (fleet.veterancy + veterancy.bonus).min(veterancy.max_bonus);
+                                }
+                                let defender = dest_planet.owner;
+                                if let Some(defender) = defender {
+                                    last_blow[defender.0] = Some(fleet.owner);
+                                }
                                 dest_planet.owner = Some(fleet.owner);
                                 dest_planet.ships = fleet.ships;
-                                messages.push(Message::AttackSucceeded(fleet.clone()));
+                                dest_planet.fuel = 0;
+                                dest_planet.veterancy = fleet.veterancy;
+                                messages.push(Message::Battle(BattleReport {
+                                    fleet: fleet.clone(),
+                                    defender,
+                                    attackers,
+                                    defenders,
+                                    attacker_losses: attackers - fleet.ships,
+                                    defender_losses: defenders,
+                                    rounds,
+                                    captured: true,
+                                }));
+                                if let Some(defender) = defender {
+                                    messages.push(Message::PlanetAttacked(Defence {
+                                        planet: fleet.destination,
+                                        defender,
+                                        attacker: fleet.owner,
+                                        held: false,
+                                        ships_left: fleet.ships,
+                                    }));
+                                }
                                 break;
                             }
-                            dest_planet.ships -= 1;
+                            take_up_to(&mut dest_planet.ships, 1);
+                            self._ships_destroyed[fleet.owner.0] += 1;
+                            if let Some(defender) = dest_planet.owner {
+                                self._ships_lost[defender.0] += 1;
+                            }
                         }
                     }
                 }
             }
         }
+        self._timings.combat += combat_start.elapsed();
+        self._timings.turns += 1;
+        for (planet, owner_before) in self._planets.iter_mut().zip(owners_before) {
+            if planet.owner.is_some() && planet.owner == owner_before {
+                planet.held_for += 1;
+            } else {
+                planet.held_for = 0;
+            }
+        }
         let new_fleets = self._fleets.drain(..)
                                     .filter(|f| f.turns_to_arrival > 0 && f.ships > 0)
                                     .collect();
 
+        self._fleets = new_fleets;
+
         let alive_after = self.remaining_players();
         alive_before
             .difference(&alive_after)
             .for_each(|player_index| {
-                messages.push(Message::PlayerEliminated(self._players[player_index.0].clone()));
+                messages.push(Message::PlayerEliminated(Elimination {
+                    player: self._players[player_index.0].clone(),
+                    by: last_blow[player_index.0],
+                    turn: self._turn,
+                    ships_built: self._produced[player_index.0],
+                    ships_lost: self._ships_lost[player_index.0],
+                    allies_remain: alive_after.iter().any(|&id| self.allied(id, *player_index)),
+                }));
             });
-        self._fleets = new_fleets;
-        messages
+        if self._rules.turn_limit == Some(self._turn) {
+            let scores = self.scores().into_iter()
+                .map(|(player, score)| (self._players[player.0].clone(), score))
+                .collect();
+            messages.push(Message::FinalScores(scores));
+        }
+        let events: Vec<Event> = messages.into_iter().enumerate()
+            .map(|(sequence, message)| Event { turn: self._turn, sequence, message })
+            .collect();
+        self._turn += 1;
+        // Observers see the game, so they are set aside while they look
+        let mut observers = std::mem::take(&mut self._observers);
+        for observer in observers.0.iter_mut() {
+            observer.turn_resolved(self, &events);
+        }
+        self._observers = observers;
+        events
+    }
+
+    // The observer is told about every turn resolved from now on
+    pub fn subscribe(&mut self, observer: Box<dyn GameObserver>) {
+        self._observers.0.push(observer);
     }
 
     pub fn new<R: Rng>(
@@ -152,9 +793,10 @@ impl Game {
         h: usize,
         players: Vec<Player>,
         neutral_planets: usize,
+        rules: GameRules,
         rng: &mut R
     ) -> Result<Game, CouldNotCreateGame> {
-        let total_planets = players.len() as usize + neutral_planets;
+        let total_planets = players.len() + neutral_planets;
         if total_planets > w * h {
             return Err(CouldNotCreateGame::TooManyPlanets);
         }
@@ -164,44 +806,186 @@ impl Game {
                 (x, y)
             })
         }).collect();
-        let mut positions = all_positions.choose_multiple(rng, total_planets as usize);
-        let mut names = PLANET_NAMES.chars();
+        let mut positions = all_positions.choose_multiple(rng, total_planets);
+        let mut names = (0..).map(planet_name);
         for (id, _player) in players.iter().enumerate() {
             planets.push(Planet {
-                name: names.next().expect("Ran out of planet names!").into(),
-                ships: 10,
-                strength: 40,
-                production: 10,
+                name: names.next().expect("Planet names never run out"),
+                ships: rules.home_ships,
+                strength: rules.home_strength,
+                production: rules.home_production,
                 pos: *positions.next().expect("Not enough positions!?"),
                 owner: Some(PlayerId(id)),
+                held_for: 0,
+                fuel: 0,
+                veterancy: 0,
+                terrain: Terrain::Open,
             });
         }
         let strength_distribution = Binomial::new(100, 0.55).expect("Static binomial parameters should be ok!");
         let production_distribution = Binomial::new(10, 0.5).expect("Static binomial parameters should be ok!");
         positions.map(|pos| Planet {
-            name: names.next().expect("Ran out of planet names!").into(),
+            name: names.next().expect("Planet names never run out"),
             ships: 0,
             strength: rng.sample(strength_distribution) as usize,
             production: rng.sample(production_distribution) as usize + 5,
             pos: *pos,
             owner: None,
+            held_for: 0,
+            fuel: 0,
+            veterancy: 0,
+            // One in five neutral planets lies in a nebula and another one
+            // in five in an asteroid belt
+            terrain: if !rules.terrain {
+                Terrain::Open
+            } else {
+                match rng.gen_range(0..5) {
+                    0 => Terrain::Nebula,
+                    1 => Terrain::AsteroidBelt,
+                    _ => Terrain::Open,
+                }
+            },
         }).for_each(|p| planets.push(p));
+        let hill = match rules.victory {
+            VictoryCondition::KingOfTheHill { .. } => {
+                let centre = ((w as f32 - 1.0) / 2.0, (h as f32 - 1.0) / 2.0);
+                let distance_to_centre = |p: &Planet| {
+                    let dx = p.pos.0 as f32 - centre.0;
+                    let dy = p.pos.1 as f32 - centre.1;
+                    dx * dx + dy * dy
+                };
+                let hill = planets.iter().enumerate()
+                    .filter(|(_, p)| p.owner.is_none())
+                    .min_by(|(_, a), (_, b)| distance_to_centre(a).total_cmp(&distance_to_centre(b)))
+                    .map(|(id, _)| PlanetId(id));
+                Some(hill.ok_or(CouldNotCreateGame::NoNeutralPlanetForHill)?)
+            }
+            VictoryCondition::LastPlayerStanding | VictoryCondition::Economic { .. } => None,
+        };
+        let produced = vec![0; players.len()];
+        let ships_lost = vec![0; players.len()];
+        let ships_destroyed = vec![0; players.len()];
+        let planet_ids = planets.iter().enumerate()
+            .map(|(id, planet)| (planet.name.clone(), PlanetId(id)))
+            .collect();
         Ok(Game {
+            _rules: rules,
             _planets: planets,
+            _planet_ids: planet_ids,
             _players: players,
             _fleets: Vec::new(),
+            _next_fleet_id: 0,
             _queued_commands: vec![],
+            _hill: hill,
+            _produced: produced,
+            _ships_lost: ships_lost,
+            _ships_destroyed: ships_destroyed,
+            _turn: 1,
+            _surrenders: vec![],
+            _vassals: BTreeSet::new(),
+            _alliances: BTreeSet::new(),
+            _proposals: BTreeSet::new(),
+            _diplomacy: vec![],
+            _timings: PhaseTimings::default(),
+            _observers: Observers::default(),
+            _scoring: SharedScoring::default(),
             _w: w,
             _h: h,
         })
     }
 
+    // Whether the planet belongs to another player and lies within `radius`
+    // turns of that player's home planet
+    fn near_home(&self, planet_id: PlanetId, radius: usize, attacker: PlayerId) -> bool {
+        let planet = &self._planets[planet_id.0];
+        match planet.owner {
+            // Players' home planets are created first, in player order
+            Some(owner) if !self.allied(owner, attacker) => distance(planet, &self._planets[owner.0]) <= radius,
+            _ => false,
+        }
+    }
+
+    fn within_range(&self, a: &Planet, b: &Planet) -> bool {
+        self._rules.max_range.is_none_or(|range| distance(a, b) <= range)
+    }
+
+    // Whether the player can see the planet through the fog of war. Allies
+    // share what they see, and once a whole team is out its players watch
+    // with nothing hidden.
+    pub fn visible(&self, player_id: PlayerId, planet_id: PlanetId) -> bool {
+        let sensor_range = match self._rules.fog {
+            Some(range) => range,
+            None => return true,
+        };
+        let target = match self._planets.get(planet_id.0) {
+            Some(planet) => planet,
+            None => return false,
+        };
+        if !self.side_remains(player_id) {
+            return true;
+        }
+        self._planets.iter()
+            .filter(|p| p.owner.is_some_and(|owner| self.allied(player_id, owner)))
+            .any(|p| distance(p, target) <= sensor_range)
+    }
+
+    // Whether the player can see the fleet through the fog of war
+    pub fn fleet_visible(&self, player_id: PlayerId, fleet: &Fleet) -> bool {
+        self.shares_vision(player_id, fleet.owner)
+    }
+
+    // Whether the player sees everything the other player has, fleets and
+    // figures included: without fog, between allies, and when watching
+    pub fn shares_vision(&self, player_id: PlayerId, other: PlayerId) -> bool {
+        self._rules.fog.is_none() || self.allied(player_id, other) || !self.side_remains(player_id)
+    }
+
+    // Teammates, and players who made an alliance in the game
+    pub fn allied(&self, a: PlayerId, b: PlayerId) -> bool {
+        allied(&self._players, &self._alliances, a, b)
+    }
+
+    // Whether the player or any of their allies are still in the game
+    pub fn side_remains(&self, player_id: PlayerId) -> bool {
+        self.remaining_players().iter().any(|&id| self.allied(player_id, id))
+    }
+
+    // Whether any of the player's planets could send a fleet to the planet
+    pub fn in_range(&self, player_id: PlayerId, planet_id: PlanetId) -> bool {
+        match self._planets.get(planet_id.0) {
+            Some(target) => self._planets.iter()
+                .filter(|p| p.owner == Some(player_id))
+                .any(|p| self.within_range(p, target)),
+            None => false,
+        }
+    }
+
     pub fn queue_fleet(
         &mut self,
         player_id: PlayerId,
         source_planet_id: PlanetId,
         destination_planet_id: PlanetId,
         count: usize,
+        name: Option<String>,
+    ) -> Result<(), CouldNotSend> {
+        self.check_fleet(player_id, source_planet_id, destination_planet_id, count, name.as_deref())?;
+        self._queued_commands.push((player_id, SendShipsCommand {
+            source_planet_id,
+            destination_planet_id,
+            count,
+            name,
+        }));
+        Ok(())
+    }
+
+    // Checks whether queue_fleet would accept the order, without queueing it
+    pub fn check_fleet(
+        &self,
+        player_id: PlayerId,
+        source_planet_id: PlanetId,
+        destination_planet_id: PlanetId,
+        count: usize,
+        name: Option<&str>,
     ) -> Result<(), CouldNotSend> {
         if self._planets.len() <= source_planet_id.0 || self._planets.len() <= destination_planet_id.0 {
             return Err(CouldNotSend::NoSuchPlanet)
@@ -209,69 +993,531 @@ impl Game {
         if self._planets[source_planet_id.0].owner != Some(player_id) {
             return Err(CouldNotSend::NotYourPlanet)
         }
+        // A fleet sent to where it already is would arrive before it left
+        if source_planet_id == destination_planet_id {
+            return Err(CouldNotSend::SamePlanet)
+        }
+        if count == 0 {
+            return Err(CouldNotSend::NoShips)
+        }
+        if !self.within_range(&self._planets[source_planet_id.0], &self._planets[destination_planet_id.0]) {
+            return Err(CouldNotSend::OutOfRange)
+        }
         let planet_queued_ships: usize = self._queued_commands.iter()
             .filter(|(_player, command)| command.source_planet_id == source_planet_id)
             .map(|(_player, command)| command.count)
             .sum();
-        let planet_remaining_ships = self._planets[source_planet_id.0].ships - planet_queued_ships;
+        let planet_remaining_ships = self._planets[source_planet_id.0].ships.saturating_sub(planet_queued_ships);
         if planet_remaining_ships < count {
             return Err(CouldNotSend::NotEnoughShips)
         }
-        self._queued_commands.push((player_id, SendShipsCommand {
-            source_planet_id,
-            destination_planet_id,
-            count,
-        }));
+        let source_planet = &self._planets[source_planet_id.0];
+        let planet_queued_fuel: usize = self._queued_commands.iter()
+            .filter(|(_player, command)| command.source_planet_id == source_planet_id)
+            .map(|(_player, command)| self._rules.fuel_needed(source_planet, &self._planets[command.destination_planet_id.0]))
+            .sum();
+        if source_planet.fuel < planet_queued_fuel + self._rules.fuel_needed(source_planet, &self._planets[destination_planet_id.0]) {
+            return Err(CouldNotSend::NotEnoughFuel)
+        }
+        if let Some(protection) = self._rules.protection {
+            if self._turn <= protection.turns && self.near_home(destination_planet_id, protection.radius, player_id) {
+                return Err(CouldNotSend::HomeProtected { until_turn: protection.turns })
+            }
+        }
+        // Fleet names are unique among a player's fleets in flight and queued
+        if let Some(name) = name {
+            let in_flight = self._fleets.iter()
+                .any(|f| f.owner == player_id && f.name.as_deref() == Some(name));
+            let queued = self.queued_commands(player_id)
+                .any(|c| c.name.as_deref() == Some(name));
+            if in_flight || queued {
+                return Err(CouldNotSend::FleetNameTaken)
+            }
+        }
         Ok(())
     }
 
-    pub fn remaining_players(&self) -> HashSet<PlayerId> {
-        let players_with_planets : HashSet<PlayerId> = self._planets.iter().filter_map(|p| p.owner).collect();
-        let players_with_fleets : HashSet<PlayerId> = self._fleets.iter().map(|f| f.owner).collect();
-        players_with_planets.union(&players_with_fleets).map(|p| *p).collect()
+    // Estimates an attack on the destination as if the fleet left this
+    // turn, assuming the target keeps its ships and builds at its current
+    // rate until the fleet arrives. Other fleets are not taken into account.
+    pub fn attack_odds(&self, source_planet_id: PlanetId, destination_planet_id: PlanetId, count: usize) -> Option<BattleOdds> {
+        let source = self._planets.get(source_planet_id.0)?;
+        let destination = self._planets.get(destination_planet_id.0)?;
+        let defenders = if destination.owner.is_some() {
+            destination.ships + destination.production * distance(source, destination)
+        } else {
+            destination.ships
+        };
+        Some(battle_odds(self._rules.combat, count, destination.attack_strength(source.effective_strength()), defenders, destination.defence_strength()))
     }
 
-    pub fn get_winner(&self) -> Option<PlayerId> {
-        let players = self.remaining_players();
-        if players.len() == 1 {
-            Some(*players.iter().next().unwrap())
-        } else {
-            None
+    // What ending the turn now is likely to bring, going by what the player
+    // knows: the fleets in flight they can see and their own queued orders,
+    // but not other players' orders. Battles for the same planet are fought in the order
+    // end_turn fights them, each against the likelier result of the last.
+    // Fleets bound for planets hidden by the fog are left out.
+    pub fn preview(&self, player_id: PlayerId) -> Vec<ProjectedArrival> {
+        // Expected owner and ships of each planet, after production and
+        // launches
+        let mut garrisons: Vec<(Option<PlayerId>, usize)> = self._planets.iter()
+            .map(|p| (p.owner, p.ships + if p.owner.is_some() { p.production } else { 0 }))
+            .collect();
+        for command in self.queued_commands(player_id) {
+            take_up_to(&mut garrisons[command.source_planet_id.0].1, command.count);
+        }
+        // Fleets already in flight fight before the ones launched this turn
+        let in_flight = self._fleets.iter()
+            .filter(|f| f.turns_to_arrival <= 1 && self.fleet_visible(player_id, f))
+            .map(|f| (f.owner, f.ships, f.effective_strength(), f.source, f.destination));
+        let launching = self.queued_commands(player_id)
+            .filter(|c| distance(&self._planets[c.source_planet_id.0], &self._planets[c.destination_planet_id.0]) <= 1)
+            .map(|c| (player_id, c.count, self._planets[c.source_planet_id.0].effective_strength(), c.source_planet_id, c.destination_planet_id));
+        let mut arrivals = Vec::new();
+        let arrivals_seen = in_flight.chain(launching)
+            .filter(|&(_, _, _, _, destination)| self.visible(player_id, destination));
+        for (owner, ships, strength, source, destination) in arrivals_seen {
+            let planet = &self._planets[destination.0];
+            let garrison = &mut garrisons[destination.0];
+            let odds = if garrison.0.is_some_and(|holder| self.allied(holder, owner)) {
+                garrison.1 += ships;
+                None
+            } else {
+                let odds = battle_odds(self._rules.combat, ships, planet.attack_strength(strength), garrison.1, planet.defence_strength());
+                if odds.win_probability >= 0.5 {
+                    *garrison = (Some(owner), odds.expected_survivors.round() as usize);
+                }
+                Some(odds)
+            };
+            arrivals.push(ProjectedArrival { owner, ships, source, destination, odds });
+        }
+        arrivals
+    }
+
+    // Best called on a new game, before anyone has moved; `radius` is in
+    // turns
+    pub fn balance(&self, radius: usize) -> Vec<StartBalance> {
+        self.players().map(|(player, _)| {
+            let own: Vec<&Planet> = self._planets.iter().filter(|p| p.owner == Some(player)).collect();
+            let closest = |planet: &Planet| own.iter().map(|home| distance(home, planet)).min();
+            let neutrals: Vec<(usize, &Planet)> = self._planets.iter()
+                .filter(|p| p.owner.is_none())
+                .filter_map(|p| closest(p).map(|turns| (turns, p)))
+                .collect();
+            StartBalance {
+                player,
+                nearest_neutral: neutrals.iter().map(|(turns, _)| *turns).min(),
+                nearby_production: neutrals.iter().filter(|(turns, _)| *turns <= radius).map(|(_, p)| p.production).sum(),
+                nearest_opponent: self._planets.iter()
+                    .filter(|p| p.owner.is_some_and(|owner| !self.allied(player, owner)))
+                    .filter_map(closest)
+                    .min(),
+            }
+        }).collect()
+    }
+
+    // Fleets the player can see heading for their planets, soonest first.
+    // Garrisons are assumed to keep building until the fleet lands.
+    pub fn threats(&self, player_id: PlayerId) -> Vec<Threat> {
+        let mut threats: Vec<Threat> = self._fleets.iter()
+            .filter(|f| !self.allied(player_id, f.owner) && self.fleet_visible(player_id, f))
+            .filter_map(|fleet| {
+                let planet = &self._planets[fleet.destination.0];
+                if planet.owner != Some(player_id) {
+                    return None
+                }
+                let defenders = planet.ships + planet.production * fleet.turns_to_arrival;
+                Some(Threat {
+                    fleet: fleet.id,
+                    owner: fleet.owner,
+                    ships: fleet.ships,
+                    destination: fleet.destination,
+                    turns_to_arrival: fleet.turns_to_arrival,
+                    odds: battle_odds(self._rules.combat, fleet.ships, planet.attack_strength(fleet.effective_strength()), defenders, planet.defence_strength()),
+                })
+            })
+            .collect();
+        threats.sort_by_key(|t| t.turns_to_arrival);
+        threats
+    }
+
+    // Takes effect when the turn ends, before any fleets launch
+    pub fn surrender(&mut self, player_id: PlayerId, to: PlayerId) -> Result<(), CouldNotSurrender> {
+        if self._players.len() <= player_id.0 || self._players.len() <= to.0 {
+            return Err(CouldNotSurrender::NoSuchPlayer)
+        }
+        if player_id == to {
+            return Err(CouldNotSurrender::CannotSurrenderToSelf)
+        }
+        let remaining = self.remaining_players();
+        if !remaining.contains(&player_id) || !remaining.contains(&to) {
+            return Err(CouldNotSurrender::PlayerEliminated)
+        }
+        if self._surrenders.iter().any(|(loser, winner)| *loser == player_id || *loser == to || *winner == player_id) {
+            return Err(CouldNotSurrender::AlreadySurrendered)
+        }
+        self._surrenders.push((player_id, to));
+        Ok(())
+    }
+
+    // Plays the next `turns` turns out on a copy of the game, as far as
+    // `player` can tell: their queued orders and `orders` are carried out,
+    // other players' queued orders are ignored and nobody gives new ones
+    pub fn simulate<R: Rng>(&self, player: PlayerId, orders: &[SendShipsCommand], turns: usize, rng: &mut R) -> Game {
+        let mut game = self.clone();
+        game._queued_commands.retain(|(owner, _)| *owner == player);
+        game._surrenders.clear();
+        game._diplomacy.retain(|(owner, _, _)| *owner == player);
+        for order in orders {
+            // Orders that are not possible are simply not carried out
+            let _ = game.queue_fleet(player, order.source_planet_id, order.destination_planet_id, order.count, order.name.clone());
+        }
+        for _ in 0..turns {
+            game.end_turn(rng);
+        }
+        game
+    }
+
+    // Removes the player from the game straight away: their planets turn
+    // neutral with whatever ships they hold, and their fleets and orders are
+    // dropped
+    pub fn forfeit(&mut self, player_id: PlayerId) {
+        for planet in self._planets.iter_mut().filter(|p| p.owner == Some(player_id)) {
+            planet.owner = None;
+            planet.held_for = 0;
+        }
+        self._fleets.retain(|f| f.owner != player_id);
+        self._queued_commands.retain(|(player, _)| *player != player_id);
+        self._surrenders.retain(|(loser, winner)| *loser != player_id && *winner != player_id);
+        self._diplomacy.retain(|(player, other, _)| *player != player_id && *other != player_id);
+    }
+
+    // Drops every order, surrender and diplomatic move made this turn
+    pub fn clear_orders(&mut self) {
+        self._queued_commands.clear();
+        self._surrenders.clear();
+        self._diplomacy.clear();
+    }
+
+    // Takes back one of the player's orders for this turn; `index` counts
+    // the player's own orders in the order queued_commands lists them
+    pub fn cancel_queued(&mut self, player_id: PlayerId, index: usize) -> Result<SendShipsCommand, CouldNotCancel> {
+        let position = self._queued_commands.iter()
+            .enumerate()
+            .filter(|(_, (player, _))| *player == player_id)
+            .nth(index)
+            .map(|(position, _)| position)
+            .ok_or(CouldNotCancel::NoSuchOrder)?;
+        Ok(self._queued_commands.remove(position).1)
+    }
+
+    pub fn queued_commands(&self, player_id: PlayerId) -> impl Iterator<Item = &SendShipsCommand> {
+        self._queued_commands.iter()
+            .filter(move |(player, _)| *player == player_id)
+            .map(|(_, command)| command)
+    }
+
+    // Offers an alliance to another player; it is made once both have
+    // offered, and announced to everyone when the turn ends
+    pub fn propose_alliance(&mut self, player_id: PlayerId, to: PlayerId) -> Result<(), CouldNotAlly> {
+        self.check_diplomacy(player_id, to)?;
+        if self.take_back(player_id, to, Diplomacy::BreakAlliance) {
+            return Ok(())
+        }
+        if self.allied(player_id, to) {
+            return Err(CouldNotAlly::AlreadyAllied)
+        }
+        if self._proposals.contains(&(player_id, to)) || self.queued_diplomacy(player_id).any(|(other, _)| other == to) {
+            return Err(CouldNotAlly::AlreadyProposed)
+        }
+        self._diplomacy.push((player_id, to, Diplomacy::ProposeAlliance));
+        Ok(())
+    }
+
+    // Ends an alliance, or withdraws an offer of one, when the turn ends.
+    // Teams stay together for the whole game.
+    pub fn break_alliance(&mut self, player_id: PlayerId, with: PlayerId) -> Result<(), CouldNotAlly> {
+        self.check_diplomacy(player_id, with)?;
+        if same_team(&self._players, player_id, with) {
+            return Err(CouldNotAlly::SameTeam)
+        }
+        if self.take_back(player_id, with, Diplomacy::ProposeAlliance) {
+            return Ok(())
+        }
+        let breaking = self.queued_diplomacy(player_id).any(|(other, _)| other == with);
+        if breaking || !self.allied(player_id, with) && !self._proposals.contains(&(player_id, with)) {
+            return Err(CouldNotAlly::NotAllied)
+        }
+        self._diplomacy.push((player_id, with, Diplomacy::BreakAlliance));
+        Ok(())
+    }
+
+    // Cancels a move made about the other player earlier this turn
+    fn take_back(&mut self, player_id: PlayerId, other: PlayerId, action: Diplomacy) -> bool {
+        let queued = self._diplomacy.len();
+        self._diplomacy.retain(|entry| *entry != (player_id, other, action));
+        self._diplomacy.len() < queued
+    }
+
+    fn check_diplomacy(&self, player_id: PlayerId, other: PlayerId) -> Result<(), CouldNotAlly> {
+        if self._players.len() <= player_id.0 || self._players.len() <= other.0 {
+            return Err(CouldNotAlly::NoSuchPlayer)
         }
+        if player_id == other {
+            return Err(CouldNotAlly::CannotAllyWithSelf)
+        }
+        let remaining = self.remaining_players();
+        if !remaining.contains(&player_id) || !remaining.contains(&other) {
+            return Err(CouldNotAlly::PlayerEliminated)
+        }
+        Ok(())
+    }
+
+    pub fn alliances(&self) -> impl Iterator<Item = (PlayerId, PlayerId)> + '_ {
+        self._alliances.iter().copied()
+    }
+
+    // Offers still waiting for an answer, from the first player to the second
+    pub fn alliance_proposals(&self) -> impl Iterator<Item = (PlayerId, PlayerId)> + '_ {
+        self._proposals.iter().copied()
+    }
+
+    pub fn queued_diplomacy(&self, player_id: PlayerId) -> impl Iterator<Item = (PlayerId, Diplomacy)> + '_ {
+        self._diplomacy.iter()
+            .filter(move |(player, _, _)| *player == player_id)
+            .map(|(_, other, action)| (*other, *action))
+    }
+
+    // Players who surrendered, each with the player they surrendered to
+    pub fn vassals(&self) -> impl Iterator<Item = (PlayerId, PlayerId)> + '_ {
+        self._vassals.iter().copied()
+    }
+
+    pub fn queued_surrender(&self, player_id: PlayerId) -> Option<PlayerId> {
+        self._surrenders.iter()
+            .find(|(loser, _)| *loser == player_id)
+            .map(|(_, winner)| *winner)
+    }
+
+    // Planets of the player with at least `threshold` ships that are
+    // neither sending anything this turn nor facing any of the threats
+    // the player knows of
+    pub fn idle_planets(&self, player_id: PlayerId, threshold: usize) -> Vec<PlanetId> {
+        let threatened: BTreeSet<PlanetId> = self.threats(player_id).into_iter().map(|t| t.destination).collect();
+        self.planets()
+            .filter(|(_, planet)| planet.owner == Some(player_id) && planet.ships >= threshold)
+            .filter(|(id, _)| !self._queued_commands.iter().any(|(_, c)| c.source_planet_id == *id))
+            .filter(|(id, _)| !threatened.contains(id))
+            .map(|(id, _)| id)
+            .collect()
     }
 
-    pub fn get_planet_id(&self, name : &String) -> Result<PlanetId, String> {
-        if name.len() != 1 {
-            Err("Planet names are a single character".to_string())
+    pub fn empire(&self, owner: Option<PlayerId>) -> EmpireSummary {
+        self.summarise(self.planets().filter(|(_, p)| p.owner == owner).map(|(_, p)| p).collect())
+    }
+
+    // The owner's empire as far as the viewer can see it through the fog
+    pub fn empire_seen_by(&self, viewer: PlayerId, owner: Option<PlayerId>) -> EmpireSummary {
+        self.summarise(self.planets().filter(|(id, p)| p.owner == owner && self.visible(viewer, *id)).map(|(_, p)| p).collect())
+    }
+
+    fn summarise(&self, planets: Vec<&Planet>) -> EmpireSummary {
+        let centre = if planets.is_empty() {
+            None
         } else {
-            PLANET_NAMES.chars()
-                        .take(self._planets.len())
-                        .position(|c| c.to_string() == *name)
-                        .map(PlanetId)
-                        .ok_or("no such planet".to_string())
+            let n = planets.len() as f32;
+            Some((
+                planets.iter().map(|p| p.pos.0 as f32).sum::<f32>() / n,
+                planets.iter().map(|p| p.pos.1 as f32).sum::<f32>() / n,
+            ))
+        };
+        EmpireSummary {
+            planets: planets.len(),
+            ships: planets.iter().map(|p| p.ships).sum(),
+            production: planets.iter().map(|p| p.production).sum(),
+            centre,
+        }
+    }
+
+    // The neutral planet with the highest production that the player can
+    // see, preferring weaker defences when production is equal
+    pub fn largest_neutral_prize(&self, player_id: PlayerId) -> Option<PlanetId> {
+        self.planets()
+            .filter(|(id, p)| p.owner.is_none() && self.visible(player_id, *id))
+            .max_by(|(_, a), (_, b)| a.production.cmp(&b.production).then(b.strength.cmp(&a.strength)))
+            .map(|(id, _)| id)
+    }
+
+    // Ordered by PlayerId, so anything iterating over it (turn order,
+    // elimination messages) comes out the same way every time
+    pub fn remaining_players(&self) -> BTreeSet<PlayerId> {
+        self._planets.iter().filter_map(|p| p.owner)
+            .chain(self._fleets.iter().map(|f| f.owner))
+            .collect()
+    }
+
+    // A player who wins on a team wins for the whole team, and allies left
+    // on their own win together. Alliances do not carry over: an ally of an
+    // ally is not a friend, so every pair left has to be allied.
+    pub fn get_winner(&self) -> Option<Winner> {
+        let players = self.remaining_players();
+        let first = *players.first()?;
+        let all_allied = players.iter().all(|&a| players.iter().all(|&b| self.allied(a, b)));
+        if all_allied && !players.iter().all(|&id| same_team(&self._players, first, id)) {
+            return Some(Winner::Alliance(players.into_iter().collect()))
+        }
+        let winner = self.get_winning_player()?;
+        Some(match self._players[winner.0].team {
+            Some(team) => Winner::Team(team),
+            None => Winner::Player(winner),
+        })
+    }
+
+    fn get_winning_player(&self) -> Option<PlayerId> {
+        let players = self.remaining_players();
+        let first = *players.first()?;
+        if players.iter().all(|&id| same_team(&self._players, first, id)) {
+            return Some(first)
+        }
+        let winner = match self._rules.victory {
+            VictoryCondition::LastPlayerStanding => None,
+            VictoryCondition::KingOfTheHill { turns } => {
+                let hill = &self._planets[self._hill?.0];
+                hill.owner.filter(|_| hill.held_for >= turns)
+            }
+            VictoryCondition::Economic { target } => {
+                // Several players can pass the target in the same turn; the
+                // one who built the most wins, ties going to the lowest id
+                let best = self._produced.iter().enumerate()
+                    .filter(|(id, _)| players.contains(&PlayerId(*id)))
+                    .max_by(|(ia, a), (ib, b)| a.cmp(b).then(ib.cmp(ia)))?;
+                Some(PlayerId(best.0)).filter(|_| *best.1 >= target)
+            }
+        };
+        match self._rules.turn_limit {
+            // Players out of the game cannot win it, however they scored
+            Some(limit) if winner.is_none() && self._turn > limit => self.scores().into_iter()
+                .map(|(player, _)| player)
+                .find(|player| players.contains(player)),
+            _ => winner,
+        }
+    }
+
+    // Every player's score for deciding games at the turn limit, best first
+    // and ties going to the lowest id
+    pub fn scores(&self) -> Vec<(PlayerId, f64)> {
+        let mut scores: Vec<(PlayerId, f64)> = (0..self._players.len())
+            .map(|id| (PlayerId(id), self._scoring.0.score(self, PlayerId(id))))
+            .collect();
+        scores.sort_by(|(ia, a), (ib, b)| b.total_cmp(a).then(ia.cmp(ib)));
+        scores
+    }
+
+    // Everyone who shares in the victory, eliminated teammates included
+    pub fn winning_players(&self, winner: Winner) -> Vec<PlayerId> {
+        match winner {
+            Winner::Player(id) => vec![id],
+            Winner::Team(team) => self.team(team),
+            Winner::Alliance(players) => players,
+        }
+    }
+
+    pub fn team(&self, team: usize) -> Vec<PlayerId> {
+        self.players().filter(|(_, p)| p.team == Some(team)).map(|(id, _)| id).collect()
+    }
+
+    pub fn winner_name(&self, winner: Winner) -> String {
+        match winner {
+            Winner::Player(id) => self._players[id.0].name.clone(),
+            Winner::Team(team) => {
+                let names: Vec<&str> = self.team(team).iter().map(|id| self._players[id.0].name.as_str()).collect();
+                format!("team {} ({})", team, names.join(", "))
+            }
+            Winner::Alliance(players) => {
+                let names: Vec<&str> = players.iter().map(|id| self._players[id.0].name.as_str()).collect();
+                format!("the alliance of {}", names.join(", "))
+            }
+        }
+    }
+
+    // Ranks the players from now on, and decides the game at the turn limit
+    pub fn set_scoring(&mut self, scoring: Rc<dyn Scoring>) {
+        self._scoring = SharedScoring(scoring);
+    }
+
+    pub fn scoring(&self) -> &Rc<dyn Scoring> {
+        &self._scoring.0
+    }
+
+    pub fn produced(&self, player: PlayerId) -> usize {
+        self._produced.get(player.0).copied().unwrap_or(0)
+    }
+
+    pub fn stats(&self, player: PlayerId) -> PlayerStats {
+        let empire = self.empire(Some(player));
+        let fleets: Vec<&Fleet> = self._fleets.iter().filter(|f| f.owner == player).collect();
+        PlayerStats {
+            planets: empire.planets,
+            ships: empire.ships + fleets.iter().map(|f| f.ships).sum::<usize>(),
+            production: empire.production,
+            fleets_in_flight: fleets.len(),
+            ships_built: self.produced(player),
+            ships_lost: self._ships_lost.get(player.0).copied().unwrap_or(0),
+            ships_destroyed: self._ships_destroyed.get(player.0).copied().unwrap_or(0),
         }
     }
 
+    // Players still in the game with their statistics, the most planets
+    // first, then the most ships
+    pub fn standings(&self) -> Vec<(PlayerId, PlayerStats)> {
+        let mut standings: Vec<(PlayerId, PlayerStats)> = self.remaining_players().into_iter()
+            .map(|id| (id, self.stats(id)))
+            .collect();
+        standings.sort_by(|(ia, a), (ib, b)| b.planets.cmp(&a.planets).then(b.ships.cmp(&a.ships)).then(ia.cmp(ib)));
+        standings
+    }
+
+    // The planet that has to be held in king-of-the-hill games
+    pub fn hill(&self) -> Option<PlanetId> {
+        self._hill
+    }
+
+    // Names are matched whatever their case, since the map shows planets
+    // out of range in lower case
+    pub fn get_planet_id(&self, name: &str) -> Result<PlanetId, String> {
+        self._planet_ids.get(name)
+            .or_else(|| self._planet_ids.get(&name.to_ascii_uppercase()))
+            .copied()
+            .ok_or("no such planet".to_string())
+    }
+
+    pub fn fleets(&self) -> impl Iterator<Item = &Fleet> {
+        self._fleets.iter()
+    }
+
     pub fn planets(&self) -> impl Iterator<Item = (PlanetId, &Planet)> {
         self._planets.iter().enumerate().map(|(id, planet)| (PlanetId(id), planet))
     }
-    pub fn planet(&self, id: PlanetId) -> Result<&Planet, ()> {
-        if self._planets.len() <= id.0 {
-            Err(())
-        } else {
-            Ok(&self._planets[id.0])
-        }
+    pub fn planet(&self, id: PlanetId) -> Option<&Planet> {
+        self._planets.get(id.0)
     }
 
     pub fn players(&self) -> impl Iterator<Item = (PlayerId, &Player)> {
         self._players.iter().enumerate().map(|(id, player)| (PlayerId(id), player))
     }
-    pub fn player(&self, id: PlayerId) -> Result<&Player, ()> {
-        if self._players.len() <= id.0 {
-            Err(())
-        } else {
-            Ok(&self._players[id.0])
-        }
+    pub fn player(&self, id: PlayerId) -> Option<&Player> {
+        self._players.get(id.0)
+    }
+
+    pub fn turn(&self) -> usize {
+        self._turn
+    }
+
+    pub fn timings(&self) -> &PhaseTimings {
+        &self._timings
+    }
+
+    pub fn rules(&self) -> &GameRules {
+        &self._rules
     }
 
     pub fn size(&self) -> (usize, usize) {
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+
+use rand::Rng;
+
+use crate::controller::PlayerController;
+use crate::game::{Game, PlayerId, Winner};
+
+// Games still undecided after this many turns count as a draw
+pub const DEFAULT_TURN_LIMIT: usize = 500;
+
+// Plays the game out between computer players with no input or output,
+// until someone wins or `turn_limit` turns have been played. Players
+// without a controller never give orders.
+pub fn play_headless<R: Rng>(
+    game: &mut Game,
+    controllers: &mut HashMap<PlayerId, Box<dyn PlayerController>>,
+    turn_limit: usize,
+    rng: &mut R,
+) -> Option<Winner> {
+    while game.get_winner().is_none() && game.turn() <= turn_limit {
+        for player_id in game.remaining_players() {
+            if let Some(controller) = controllers.get_mut(&player_id) {
+                for command in controller.take_turn(game, player_id) {
+                    // Invalid orders are dropped
+                    let _ = game.queue_fleet(player_id, command.source_planet_id, command.destination_planet_id, command.count, command.name);
+                }
+            }
+        }
+        game.end_turn(rng);
+    }
+    game.get_winner()
+}
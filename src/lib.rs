@@ -0,0 +1,18 @@
+extern crate rand;
+extern crate rand_distr;
+extern crate serde;
+extern crate serde_json;
+extern crate terminal_size;
+
+pub mod game;
+pub mod cli;
+pub mod commentary;
+pub mod report;
+pub mod controller;
+pub mod observer;
+pub mod ai;
+pub mod scoring;
+pub mod headless;
+pub mod series;
+pub mod protocol;
+pub mod replay;
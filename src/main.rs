@@ -1,18 +1,433 @@
-use rand::thread_rng;
-use crate::{cli::Cli, game::{Game, Player}};
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
 
+use rand::{Rng, SeedableRng, thread_rng};
+use rand::rngs::StdRng;
+use clonequest::ai::{DefensiveBot, GreedyBot, LookaheadBot, RandomBot};
+use clonequest::cli::{self, Cli, Preferences};
+use clonequest::commentary::{Commentator, MessageFormatter, PlainFormatter};
+use clonequest::controller::PlayerController;
+use clonequest::headless::{DEFAULT_TURN_LIMIT, play_headless};
+use clonequest::game::{CombatModel, FuelRules, Game, GameRules, Player, PlayerId, ProtectionRules, VeterancyRules, VictoryCondition, Winner};
+use clonequest::replay::Replay;
+use clonequest::report::WarReport;
+use clonequest::series::Series;
+
+extern crate clonequest;
 extern crate rand;
-extern crate rand_distr;
 
-mod game;
-mod cli;
+fn usage_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    eprintln!("Usage: clonequest [--king-of-the-hill TURNS | --economic SHIPS] [--max-range TURNS]
+                  [--fuel FREE_RANGE PER_TURN] [--veterans BONUS MAX] [--terrain]
+                  [--protection TURNS RADIUS] [--fog RANGE] [--max-turns TURNS]
+                  [--normalised-strength]
+                  [--no-confirm] [--no-predict] [--commentary] [--timing]
+                  [--war-report FILE] [--record FILE] [--replay FILE] [--balance]
+                  [--idle-warning SHIPS | --no-idle-warning]
+                  [--clock MINUTES INCREMENT_SECONDS] [--seed SEED]
+                  [--bot random|greedy|defensive|lookahead]...
+                  [--bots COUNT random|greedy|defensive|lookahead]
+                  [--players COUNT] [--teams COUNT] [--map WIDTH HEIGHT NEUTRAL_PLANETS]
+                  [--preferences FILE]
+                  [--best-of GAMES] [--headless [--games COUNT] [--turn-limit TURNS]]
+       clonequest tournament [--games COUNT] [--turn-limit TURNS] [--seed SEED]
+                  [--map WIDTH HEIGHT NEUTRAL_PLANETS] BOT BOT...");
+    process::exit(2)
+}
+
+fn make_bot(kind: &str, seed: u64) -> Box<dyn PlayerController> {
+    match kind {
+        "random" => Box::new(RandomBot::new(seed)),
+        "greedy" => Box::new(GreedyBot),
+        "defensive" => Box::new(DefensiveBot),
+        "lookahead" => Box::new(LookaheadBot::new(seed)),
+        _ => usage_error(&format!("Unknown bot {}", kind)),
+    }
+}
+
+// Bots take over players starting from the last one. `seating` gives the
+// player in each seat of the game, so bots follow their player from seat
+// to seat.
+fn make_bots<R: Rng>(game: &Game, seating: &[usize], kinds: &[String], rng: &mut R) -> HashMap<PlayerId, Box<dyn PlayerController>> {
+    let player_ids: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+    let mut seats: Vec<usize> = (0..seating.len()).collect();
+    seats.sort_by_key(|&seat| std::cmp::Reverse(seating[seat]));
+    seats.into_iter().zip(kinds).map(|(seat, kind)| (player_ids[seat], make_bot(kind, rng.gen()))).collect()
+}
+
+// Plays every bot against every other bot, one on one, and prints how often
+// each won
+fn tournament(mut args: impl Iterator<Item = String>) {
+    let mut games = 10;
+    let mut turn_limit = DEFAULT_TURN_LIMIT;
+    let mut seed = None;
+    let mut map = (8, 8, 5);
+    let mut kinds = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => {
+                games = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--games needs a number of games"));
+            }
+            "--turn-limit" => {
+                turn_limit = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--turn-limit needs a number of turns"));
+            }
+            "--seed" => {
+                seed = Some(args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--seed needs a number")));
+            }
+            "--map" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--map needs a width, a height and a number of neutral planets"));
+                map = (number(), number(), number());
+            }
+            _ if arg.starts_with("--") => usage_error(&format!("Unknown argument {}", arg)),
+            _ => {
+                // Catch unknown bots before playing anything
+                make_bot(&arg, 0);
+                kinds.push(arg);
+            }
+        }
+    }
+    if kinds.len() < 2 {
+        usage_error("A tournament needs at least two bots");
+    }
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    println!("Tournament seed: {}", seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (w, h, neutral_planets) = map;
+    // wins[i][j] is how many games bot i won against bot j
+    let mut wins = vec![vec![0; kinds.len()]; kinds.len()];
+    for i in 0..kinds.len() {
+        for j in i + 1..kinds.len() {
+            for game_number in 0..games {
+                // Take turns being the first player
+                let seats = if game_number % 2 == 0 { [i, j] } else { [j, i] };
+                let players = seats.iter().map(|seat| Player { name: format!("{}", seat), team: None }).collect();
+                let mut game = Game::new(w, h, players, neutral_planets, GameRules::default(), &mut rng)
+                    .unwrap_or_else(|e| usage_error(&format!("Could not create the game: {:?}", e)));
+                let mut controllers: HashMap<PlayerId, Box<dyn PlayerController>> = game.players()
+                    .map(|(id, _)| id)
+                    .zip(seats)
+                    .map(|(id, seat)| (id, make_bot(&kinds[seat], rng.gen())))
+                    .collect();
+                if let Some(Winner::Player(winner)) = play_headless(&mut game, &mut controllers, turn_limit, &mut rng) {
+                    let seat = game.players().position(|(id, _)| id == winner).expect("The winner is a player");
+                    wins[seats[seat]][seats[1 - seat]] += 1;
+                }
+            }
+        }
+    }
+    let label = |i: usize| format!("{}. {}", i + 1, kinds[i]);
+    print!(" {: <14} |", "Wins against");
+    for i in 0..kinds.len() {
+        print!(" {: >4} |", i + 1);
+    }
+    println!(" Overall");
+    for (i, row) in wins.iter().enumerate() {
+        print!(" {: <14} |", label(i));
+        for (j, won) in row.iter().enumerate() {
+            if i == j {
+                print!(" {: >4} |", "-");
+            } else {
+                print!(" {: >3}% |", won * 100 / games.max(1));
+            }
+        }
+        let played = games * (kinds.len() - 1);
+        println!(" {}%", row.iter().sum::<usize>() * 100 / played.max(1));
+    }
+    println!("{} games per pairing; games undecided after {} turns count as a win for nobody.", games, turn_limit);
+}
 
 fn main() {
-    let players = vec![
-        Player {name: "Alice".into()},
-        Player {name: "Bob".into()},
-        Player {name: "Charlotte".into()},
-    ];
-    let game = Game::new(8, 8, players, 5, &mut thread_rng()).unwrap();
-    Cli::new(game).play()
+    let mut rules = GameRules::default();
+    let mut confirm_turn_end = None;
+    let mut predict_battles = None;
+    let mut commentary = false;
+    let mut timing = false;
+    let mut balance = false;
+    let mut war_report = None;
+    let mut replay = None;
+    let mut playback = None;
+    let mut idle_warning = None;
+    // Preferences are kept in the home directory unless told otherwise
+    let mut preferences_file = env::var("HOME").ok().map(|home| format!("{}/.clonequest.json", home));
+    let mut clock = None;
+    let mut seed = None;
+    let mut bots = Vec::new();
+    let mut player_count = 3;
+    let mut teams: Option<usize> = None;
+    let mut map = (8, 8, 5);
+    let mut headless = false;
+    let mut games = 1;
+    let mut best_of = None;
+    let mut turn_limit = DEFAULT_TURN_LIMIT;
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().map(|arg| arg.as_str()) == Some("tournament") {
+        args.next();
+        tournament(args);
+        return;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--king-of-the-hill" => {
+                let turns = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--king-of-the-hill needs a number of turns"));
+                rules.victory = VictoryCondition::KingOfTheHill { turns };
+            }
+            "--economic" => {
+                let target = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--economic needs a number of ships"));
+                rules.victory = VictoryCondition::Economic { target };
+            }
+            "--max-range" => {
+                let range = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--max-range needs a number of turns"));
+                rules.max_range = Some(range);
+            }
+            "--fuel" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--fuel needs a free range in turns and a fuel production per turn"));
+                let free_range = number();
+                let production = number();
+                rules.fuel = Some(FuelRules { free_range, production });
+            }
+            "--veterans" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--veterans needs a strength bonus per victory and a maximum bonus"));
+                let bonus = number();
+                let max_bonus = number();
+                rules.veterancy = Some(VeterancyRules { bonus, max_bonus });
+            }
+            "--terrain" => rules.terrain = true,
+            "--normalised-strength" => rules.combat = CombatModel::NormalisedRolls,
+            "--fog" => {
+                let range = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--fog needs a sensor range in turns"));
+                rules.fog = Some(range);
+            }
+            "--max-turns" => {
+                let turns = args.next().and_then(|t| t.parse().ok()).filter(|&n| n > 0)
+                    .unwrap_or_else(|| usage_error("--max-turns needs a number of turns"));
+                rules.turn_limit = Some(turns);
+            }
+            "--protection" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--protection needs a number of turns and a radius in turns"));
+                let turns = number();
+                let radius = number();
+                rules.protection = Some(ProtectionRules { turns, radius });
+            }
+            "--no-confirm" => confirm_turn_end = Some(false),
+            "--no-predict" => predict_battles = Some(false),
+            "--commentary" => commentary = true,
+            "--timing" => timing = true,
+            "--balance" => balance = true,
+            "--war-report" => {
+                war_report = Some(args.next().unwrap_or_else(|| usage_error("--war-report needs a file name")));
+            }
+            "--replay" => {
+                playback = Some(args.next().unwrap_or_else(|| usage_error("--replay needs a file name")));
+            }
+            "--record" => {
+                replay = Some(args.next().unwrap_or_else(|| usage_error("--record needs a file name")));
+            }
+            "--idle-warning" => {
+                let threshold = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--idle-warning needs a number of ships"));
+                idle_warning = Some(Some(threshold));
+            }
+            "--no-idle-warning" => idle_warning = Some(None),
+            "--preferences" => {
+                preferences_file = Some(args.next().unwrap_or_else(|| usage_error("--preferences needs a file name")));
+            }
+            "--players" => {
+                player_count = args.next().and_then(|t| t.parse().ok()).filter(|&n| n > 0)
+                    .unwrap_or_else(|| usage_error("--players needs a number of players"));
+            }
+            "--teams" => {
+                teams = Some(args.next().and_then(|t| t.parse().ok()).filter(|&n| n > 0)
+                    .unwrap_or_else(|| usage_error("--teams needs a number of teams")));
+            }
+            "--map" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--map needs a width, a height and a number of neutral planets"));
+                map = (number(), number(), number());
+            }
+            "--bots" => {
+                let count = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--bots needs a number of bots and a kind of bot"));
+                let kind = args.next().unwrap_or_else(|| usage_error("--bots needs a number of bots and a kind of bot"));
+                bots.extend(std::iter::repeat_n(kind, count));
+            }
+            "--bot" => bots.push(args.next().unwrap_or_else(|| usage_error("--bot needs a kind of bot: random, greedy, defensive or lookahead"))),
+            "--headless" => headless = true,
+            "--best-of" => {
+                best_of = Some(args.next().and_then(|t| t.parse().ok()).filter(|&n| n > 0)
+                    .unwrap_or_else(|| usage_error("--best-of needs a number of games")));
+            }
+            "--games" => {
+                games = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--games needs a number of games"));
+            }
+            "--turn-limit" => {
+                turn_limit = args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--turn-limit needs a number of turns"));
+            }
+            "--seed" => {
+                seed = Some(args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--seed needs a number")));
+            }
+            "--clock" => {
+                let mut number = || args.next().and_then(|t| t.parse().ok())
+                    .unwrap_or_else(|| usage_error("--clock needs a time bank in minutes and an increment in seconds"));
+                let bank = Duration::from_secs(number() * 60);
+                let increment = Duration::from_secs(number());
+                clock = Some((bank, increment));
+            }
+            _ => usage_error(&format!("Unknown argument {}", arg)),
+        }
+    }
+    if teams.is_some_and(|teams| teams > player_count) {
+        usage_error(&format!("There are only {} players to make teams of", player_count));
+    }
+    if let Some(path) = playback {
+        let replay = Replay::load(&path).unwrap_or_else(|e| {
+            eprintln!("Could not read replay from {}: {}", path, e);
+            process::exit(1)
+        });
+        // Replays are told the way the game would have told them
+        let mut formatter: Box<dyn MessageFormatter> = if commentary {
+            Box::new(Commentator::new(seed.unwrap_or_else(|| thread_rng().gen())))
+        } else {
+            Box::new(PlainFormatter)
+        };
+        if let Err(e) = cli::play_replay(&replay, formatter.as_mut()) {
+            eprintln!("Could not play the replay: {}", e);
+            process::exit(1)
+        }
+        return;
+    }
+    let names = ["Alice", "Bob", "Charlotte"];
+    // Players are dealt out to the teams in turn
+    let players: Vec<Player> = (0..player_count)
+        .map(|i| Player {
+            name: names.get(i).map(|n| n.to_string()).unwrap_or(format!("Player{}", i + 1)),
+            team: teams.map(|teams| i % teams + 1),
+        })
+        .collect();
+    // Everything random in the game comes from the seed
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    println!("Game seed: {} (play the same game again with --seed {})", seed, seed);
+    let mut rng = StdRng::seed_from_u64(seed);
+    if bots.len() > players.len() {
+        usage_error(&format!("There are only {} players to give to bots", players.len()));
+    }
+    let (w, h, neutral_planets) = map;
+    // Outside a series everyone keeps their seat
+    let mut series = best_of.map(|games| Series::new(players.len(), games));
+    let seating = |series: &Option<Series>| series.as_ref().map(|s| s.seating()).unwrap_or((0..players.len()).collect());
+    let new_game = |seating: &[usize], rng: &mut StdRng| {
+        let seated = seating.iter().map(|&player| players[player].clone()).collect();
+        Game::new(w, h, seated, neutral_planets, rules.clone(), rng)
+            .unwrap_or_else(|e| usage_error(&format!("Could not create the game: {:?}", e)))
+    };
+    if headless {
+        if bots.len() != players.len() {
+            usage_error("--headless needs a bot for every player");
+        }
+        // Wins by player name
+        let mut wins: HashMap<String, usize> = HashMap::new();
+        let mut draws = 0;
+        let mut played = 0;
+        while series.as_ref().map_or(played < games, |series| !series.is_over()) {
+            let seats = seating(&series);
+            let mut game = new_game(&seats, &mut rng);
+            let mut controllers = make_bots(&game, &seats, &bots, &mut rng);
+            match play_headless(&mut game, &mut controllers, turn_limit, &mut rng) {
+                Some(winner) => for id in game.winning_players(winner) {
+                    *wins.entry(game.player(id).unwrap().name.clone()).or_default() += 1;
+                },
+                None => draws += 1,
+            }
+            if let Some(series) = &mut series {
+                series.record(&game);
+            }
+            played += 1;
+        }
+        for (player, kind) in players.iter().zip(bots.iter().rev()) {
+            println!("{} ({}): {} wins", player.name, kind, wins.get(&player.name).unwrap_or(&0));
+        }
+        println!("Draws after {} turns: {}", turn_limit, draws);
+        if let Some(series) = &series {
+            cli::print_series(series, &players);
+        }
+        return;
+    }
+    // Options given on the command line win over saved preferences
+    let mut preferences = match &preferences_file {
+        Some(path) if Path::new(path).exists() => Preferences::load(path).unwrap_or_else(|e| {
+            eprintln!("Could not read preferences from {}: {}", path, e);
+            Preferences::default()
+        }),
+        _ => Preferences::default(),
+    };
+    preferences.confirm_turn_end = confirm_turn_end.unwrap_or(preferences.confirm_turn_end);
+    preferences.predict_battles = predict_battles.unwrap_or(preferences.predict_battles);
+    preferences.idle_warning = idle_warning.unwrap_or(preferences.idle_warning);
+    loop {
+        let seats = seating(&series);
+        let game = new_game(&seats, &mut rng);
+        if balance {
+            cli::print_balance(&game);
+        }
+        let battle_seed = rng.gen();
+        let controllers = make_bots(&game, &seats, &bots, &mut rng);
+        let mut cli = Cli::new(game);
+        cli.set_seed(battle_seed);
+        for (player_id, controller) in controllers {
+            cli.set_controller(player_id, controller);
+        }
+        cli.set_preferences(preferences.clone(), preferences_file.clone());
+        if let Some(path) = &war_report {
+            // Each game of a series gets a report of its own
+            let path = match &series {
+                Some(series) => format!("{}.{}", path, series.played() + 1),
+                None => path.clone(),
+            };
+            cli.write_war_report(WarReport::new(rng.gen()), path);
+        }
+        if let Some(path) = &replay {
+            let path = match &series {
+                Some(series) => format!("{}.{}", path, series.played() + 1),
+                None => path.clone(),
+            };
+            cli.record_replay(path);
+        }
+        if commentary {
+            cli.set_formatter(Box::new(Commentator::new(rng.gen())));
+        }
+        if let Some((bank, increment)) = clock {
+            cli.set_clock(bank, increment);
+        }
+        cli.play();
+        if timing {
+            cli::print_timings(cli.game());
+        }
+        match &mut series {
+            Some(series) => {
+                series.record(cli.game());
+                cli::print_series(series, &players);
+                if series.is_over() {
+                    break;
+                }
+            }
+            None => break,
+        }
+    }
 }
@@ -0,0 +1,20 @@
+use crate::game::{Event, Game};
+
+// Hears about every turn a game resolves, without whoever ends the turn
+// having to pass the events along
+pub trait GameObserver {
+    // `game` is the game as it stands after the turn
+    fn turn_resolved(&mut self, game: &Game, events: &[Event]);
+}
+
+// The observers subscribed to a game. They belong to that game alone:
+// clones, such as the ones used for simulations and snapshots, and saved
+// games start without any.
+#[derive(Default)]
+pub struct Observers(pub Vec<Box<dyn GameObserver>>);
+
+impl Clone for Observers {
+    fn clone(&self) -> Observers {
+        Observers::default()
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use crate::game::{Event, Fleet, Game, Planet, PlanetId, Player, PlayerId, SendShipsCommand};
+
+// The messages clients and servers exchange, whatever carries them. Every
+// message travels in an Envelope stamped with the protocol version; bump
+// the version whenever a message below changes shape.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize)]
+pub struct Envelope<T> {
+    pub version: u32,
+    pub message: T,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ClientMessage {
+    Join { name: String },
+    // The orders for `turn`, replacing any sent before
+    SubmitCommands { turn: usize, commands: Vec<SendShipsCommand> },
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ServerMessage {
+    // Who has joined so far, and how many seats the game has
+    Lobby { players: Vec<Player>, seats: usize },
+    TurnResolved { turn: usize, events: Vec<Event> },
+    ViewUpdate(View),
+    Error(ProtocolError),
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ProtocolError {
+    UnsupportedVersion { supported: u32 },
+    GameFull,
+    NameTaken,
+    NotInGame,
+    // Orders for a turn other than the one being played
+    WrongTurn { current: usize },
+    InvalidCommand(String),
+}
+
+// The game as one player sees it, fog of war applied
+#[derive(Serialize, Deserialize)]
+pub struct View {
+    pub player: PlayerId,
+    pub turn: usize,
+    pub players: Vec<Player>,
+    pub planets: Vec<(PlanetId, Planet)>,
+    pub fleets: Vec<Fleet>,
+}
+
+impl View {
+    pub fn of(game: &Game, player: PlayerId) -> View {
+        View {
+            player,
+            turn: game.turn(),
+            players: game.players().map(|(_, p)| p.clone()).collect(),
+            planets: game.planets()
+                .filter(|(id, _)| game.visible(player, *id))
+                .map(|(id, p)| (id, p.clone()))
+                .collect(),
+            fleets: game.fleets().filter(|f| game.fleet_visible(player, f)).cloned().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CouldNotDecode {
+    Malformed(String),
+    UnsupportedVersion(u32),
+}
+
+pub fn encode<T: Serialize>(message: T) -> String {
+    serde_json::to_string(&Envelope { version: PROTOCOL_VERSION, message }).expect("Protocol messages serialize")
+}
+
+pub fn decode<T: for<'de> Deserialize<'de>>(text: &str) -> Result<T, CouldNotDecode> {
+    // Check the version first, so a newer peer gets told why it was refused
+    // instead of a parse error
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+    let version: Version = serde_json::from_str(text).map_err(|e| CouldNotDecode::Malformed(e.to_string()))?;
+    if version.version != PROTOCOL_VERSION {
+        return Err(CouldNotDecode::UnsupportedVersion(version.version));
+    }
+    let envelope: Envelope<T> = serde_json::from_str(text).map_err(|e| CouldNotDecode::Malformed(e.to_string()))?;
+    Ok(envelope.message)
+}
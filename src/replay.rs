@@ -0,0 +1,111 @@
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Write};
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use serde::{Deserialize, Serialize};
+
+use crate::game::{CouldNotAlly, CouldNotSend, CouldNotSurrender, Diplomacy, Event, Game, PlayerId, SendShipsCommand};
+
+// Everything that went into one turn: what the players did, and the seed
+// of the random numbers that decided its battles
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReplayTurn {
+    // Players who ran out of time during the turn
+    pub forfeits: Vec<PlayerId>,
+    pub commands: Vec<(PlayerId, SendShipsCommand)>,
+    pub surrenders: Vec<(PlayerId, PlayerId)>,
+    pub diplomacy: Vec<(PlayerId, PlayerId, Diplomacy)>,
+    pub seed: u64,
+}
+
+// A replay that does not fit the game it is played on, e.g. one recorded
+// by a different version
+#[derive(Debug)]
+pub enum CouldNotReplay {
+    InvalidOrder(CouldNotSend),
+    InvalidSurrender(CouldNotSurrender),
+    InvalidDiplomacy(CouldNotAlly),
+}
+
+impl ReplayTurn {
+    // Gives the recorded orders again and ends the turn the way it ended
+    pub fn apply(&self, game: &mut Game) -> Result<Vec<Event>, CouldNotReplay> {
+        for &player in self.forfeits.iter() {
+            game.forfeit(player);
+        }
+        for (player, command) in self.commands.iter() {
+            game.queue_fleet(*player, command.source_planet_id, command.destination_planet_id, command.count, command.name.clone())
+                .map_err(CouldNotReplay::InvalidOrder)?;
+        }
+        for &(player, to) in self.surrenders.iter() {
+            game.surrender(player, to).map_err(CouldNotReplay::InvalidSurrender)?;
+        }
+        for &(player, other, action) in self.diplomacy.iter() {
+            match action {
+                Diplomacy::ProposeAlliance => game.propose_alliance(player, other),
+                Diplomacy::BreakAlliance => game.break_alliance(player, other),
+            }.map_err(CouldNotReplay::InvalidDiplomacy)?;
+        }
+        Ok(game.end_turn(&mut StdRng::seed_from_u64(self.seed)))
+    }
+}
+
+// A game as it was played: where it started and every turn since, which
+// is enough to play it again exactly
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub start: Game,
+    pub turns: Vec<ReplayTurn>,
+    // Forfeits in the turn being played
+    #[serde(skip)]
+    forfeits: Vec<PlayerId>,
+}
+
+impl Replay {
+    // Orders already given in `start` are recorded with the rest of the
+    // turn, so they are left out of the starting position
+    pub fn new(start: &Game) -> Replay {
+        let mut start = start.clone();
+        start.clear_orders();
+        Replay {
+            start,
+            turns: Vec::new(),
+            forfeits: Vec::new(),
+        }
+    }
+
+    // Call right after Game::forfeit
+    pub fn record_forfeit(&mut self, player: PlayerId) {
+        self.forfeits.push(player);
+    }
+
+    // Call just before end_turn, with the seed of the rng it is given
+    pub fn record_turn(&mut self, game: &Game, seed: u64) {
+        let players: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+        self.turns.push(ReplayTurn {
+            forfeits: self.forfeits.drain(..).collect(),
+            commands: players.iter()
+                .flat_map(|&id| game.queued_commands(id).map(move |command| (id, command.clone())))
+                .collect(),
+            surrenders: players.iter()
+                .filter_map(|&id| game.queued_surrender(id).map(|to| (id, to)))
+                .collect(),
+            diplomacy: players.iter()
+                .flat_map(|&id| game.queued_diplomacy(id).map(move |(other, action)| (id, other, action)))
+                .collect(),
+            seed,
+        });
+    }
+
+    pub fn load(path: &str) -> io::Result<Replay> {
+        Ok(serde_json::from_reader(BufReader::new(File::open(path)?))?)
+    }
+
+    pub fn write(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut out, self)?;
+        out.flush()
+    }
+}
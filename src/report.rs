@@ -0,0 +1,186 @@
+use std::fs::File;
+use std::io::{self, Write};
+
+use crate::commentary::{Commentator, MessageFormatter};
+use crate::game::{Event, Game, Message};
+
+struct TurnRecord {
+    turn: usize,
+    // Planets owned by each player at the end of the turn, indexed by player
+    planets: Vec<usize>,
+    events: Vec<String>,
+    captures: usize,
+}
+
+// Collects a narrative of the game turn by turn, to be written out as a
+// text file once the game is over
+pub struct WarReport {
+    commentator: Commentator,
+    turns: Vec<TurnRecord>,
+}
+
+// Width of the ownership graph in characters
+const GRAPH_WIDTH: usize = 40;
+// Number of turns singled out as the bloodiest
+const KEY_TURNS: usize = 3;
+
+impl WarReport {
+    pub fn new(seed: u64) -> WarReport {
+        WarReport {
+            commentator: Commentator::new(seed),
+            turns: Vec::new(),
+        }
+    }
+
+    // Call with the events returned by end_turn for the turn that was just
+    // resolved
+    pub fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        let messages = events.iter().map(|event| &event.message);
+        let planets = game.players()
+            .map(|(id, _)| game.planets().filter(|(_, p)| p.owner == Some(id)).count())
+            .collect();
+        // Defenders' accounts repeat the battles already told by the attackers
+        let lines = messages.clone()
+            .filter(|message| !matches!(message, Message::PlanetAttacked(_)))
+            .map(|message| self.commentator.format(game, message))
+            .collect();
+        let captures = messages
+            .filter(|message| matches!(message, Message::Battle(battle) if battle.captured))
+            .count();
+        self.turns.push(TurnRecord {
+            turn: game.turn() - 1,
+            planets,
+            events: lines,
+            captures,
+        });
+    }
+
+    pub fn write(&self, game: &Game, path: &str) -> io::Result<()> {
+        let mut out = File::create(path)?;
+        writeln!(out, "WAR REPORT")?;
+        writeln!(out, "==========")?;
+        writeln!(out)?;
+        match game.get_winner() {
+            Some(winner) => writeln!(out, "After {} turns, {} stands victorious.", self.turns.len(), game.winner_name(winner))?,
+            None => writeln!(out, "After {} turns, the war is still undecided.", self.turns.len())?,
+        }
+        writeln!(out)?;
+
+        let mut bloodiest: Vec<&TurnRecord> = self.turns.iter().filter(|t| t.captures > 0).collect();
+        bloodiest.sort_by(|a, b| b.captures.cmp(&a.captures).then(a.turn.cmp(&b.turn)));
+        if !bloodiest.is_empty() {
+            writeln!(out, "Key battles")?;
+            writeln!(out, "-----------")?;
+            for record in bloodiest.iter().take(KEY_TURNS) {
+                writeln!(out, "Turn {}: {} planets changed hands.", record.turn, record.captures)?;
+            }
+            writeln!(out)?;
+        }
+
+        writeln!(out, "Turn by turn")?;
+        writeln!(out, "------------")?;
+        for record in self.turns.iter().filter(|t| !t.events.is_empty()) {
+            writeln!(out, "Turn {}:", record.turn)?;
+            for event in record.events.iter() {
+                writeln!(out, "  {}", event)?;
+            }
+        }
+        writeln!(out)?;
+
+        writeln!(out, "Planets owned")?;
+        writeln!(out, "-------------")?;
+        for line in ownership_graph(game, self.turns.iter().map(|record| (record.turn, record.planets.as_slice()))) {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// Statistics gathered over a whole game, for the summary shown at its end
+pub struct GameSummary {
+    // Planets owned by each player at the end of each turn, indexed by player
+    planets: Vec<Vec<usize>>,
+    battles: usize,
+}
+
+impl Default for GameSummary {
+    fn default() -> GameSummary {
+        GameSummary::new()
+    }
+}
+
+impl GameSummary {
+    pub fn new() -> GameSummary {
+        GameSummary {
+            planets: Vec::new(),
+            battles: 0,
+        }
+    }
+
+    // Call with the events returned by end_turn, like WarReport::record_turn
+    pub fn record_turn(&mut self, game: &Game, events: &[Event]) {
+        self.planets.push(game.players().map(|(id, _)| game.stats(id).planets).collect());
+        self.battles += events.iter()
+            .filter(|event| matches!(event.message, Message::Battle(_)))
+            .count();
+    }
+
+    pub fn print(&self, game: &Game) {
+        println!("GAME OVER");
+        let turns = game.turn() - 1;
+        match game.get_winner() {
+            Some(winner) => println!("{} won after {} turns.", game.winner_name(winner), turns),
+            None => println!("Nobody won after {} turns.", turns),
+        }
+        println!("Battles fought: {}", self.battles);
+        println!(" {: <10} | {: >12} | {: >8} | {: >6}", "Player", "Peak planets", "Built", "Lost");
+        for (index, (id, player)) in game.players().enumerate() {
+            let stats = game.stats(id);
+            let peak = self.planets.iter().map(|turn| turn[index]).max().unwrap_or(0).max(stats.planets);
+            println!(" {: <10} | {: >12} | {: >8} | {: >6}", player.name, peak, stats.ships_built, stats.ships_lost);
+        }
+        // Games that were loaded part way through only have the later turns
+        let first_turn = (turns + 1).saturating_sub(self.planets.len());
+        let history = self.planets.iter().enumerate().map(|(index, planets)| (first_turn + index, planets.as_slice()));
+        for line in ownership_graph(game, history) {
+            println!("{}", line);
+        }
+    }
+}
+
+// A key to the players, then one bar per turn made of each player's share
+// of the planets
+fn ownership_graph<'a>(game: &Game, turns: impl Iterator<Item = (usize, &'a [usize])>) -> Vec<String> {
+    let mut lines: Vec<String> = game.players().enumerate()
+        .map(|(index, (_, player))| format!("{} = {}", graph_symbol(index), player.name))
+        .collect();
+    let total_planets = game.planets().count().max(1);
+    for (turn, planets) in turns {
+        let mut bar = String::new();
+        for (index, count) in planets.iter().enumerate() {
+            let width = count * GRAPH_WIDTH / total_planets;
+            bar.extend(std::iter::repeat_n(graph_symbol(index), width));
+        }
+        lines.push(format!("{: >4} |{}", turn, bar));
+    }
+    lines
+}
+
+// The mark for the player at `index` in Game::players, different for every
+// player: punctuation first, then letters (o is taken already) and digits,
+// then the accented letters of Latin Extended and finally CJK ideographs
+pub fn graph_symbol(index: usize) -> char {
+    const SYMBOLS: &str = "#*=+%@&$~oabcdefghijklmnpqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+    const LATIN_EXTENDED: (u32, u32) = (0x100, 0x250);
+    const CJK: u32 = 0x4e00;
+    if let Some(symbol) = SYMBOLS.chars().nth(index) {
+        return symbol
+    }
+    let index = (index - SYMBOLS.len()) as u32;
+    let code = if index < LATIN_EXTENDED.1 - LATIN_EXTENDED.0 {
+        LATIN_EXTENDED.0 + index
+    } else {
+        CJK + index - (LATIN_EXTENDED.1 - LATIN_EXTENDED.0)
+    };
+    char::from_u32(code).unwrap_or('?')
+}
@@ -0,0 +1,52 @@
+use std::rc::Rc;
+
+use crate::game::{Game, PlayerId};
+
+// Rates how well a player is doing; higher is better
+pub trait Scoring {
+    fn score(&self, game: &Game, player: PlayerId) -> f64;
+    // What the score is made of, for the rules
+    fn describe(&self) -> String;
+}
+
+// The scoring a game is ranked and decided by. Copies of the game share
+// it; saved games do not keep it and start over with the default.
+#[derive(Clone)]
+pub struct SharedScoring(pub Rc<dyn Scoring>);
+
+impl Default for SharedScoring {
+    fn default() -> SharedScoring {
+        SharedScoring(Rc::new(WeightedScore::default()))
+    }
+}
+
+// Adds up planets, ships (garrisoned and in flight) and production with
+// a weight for each
+pub struct WeightedScore {
+    pub planets: f64,
+    pub ships: f64,
+    pub production: f64,
+}
+
+impl Default for WeightedScore {
+    fn default() -> WeightedScore {
+        WeightedScore {
+            planets: 10.0,
+            ships: 1.0,
+            production: 5.0,
+        }
+    }
+}
+
+impl Scoring for WeightedScore {
+    fn score(&self, game: &Game, player: PlayerId) -> f64 {
+        let stats = game.stats(player);
+        self.planets * stats.planets as f64
+            + self.ships * stats.ships as f64
+            + self.production * stats.production as f64
+    }
+
+    fn describe(&self) -> String {
+        format!("{} per planet, {} per production, {} per ship including fleets", self.planets, self.production, self.ships)
+    }
+}
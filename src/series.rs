@@ -0,0 +1,71 @@
+use crate::game::Game;
+
+// A best-of-N match: the same participants play game after game, moving
+// up a seat each time, until one of them has won most of the games or all
+// N have been played. Participants are numbered in the order of their
+// seats in the first game.
+pub struct Series {
+    best_of: usize,
+    // Games won by each participant
+    wins: Vec<usize>,
+    draws: usize,
+    played: usize,
+}
+
+impl Series {
+    pub fn new(participants: usize, best_of: usize) -> Series {
+        Series {
+            best_of,
+            wins: vec![0; participants],
+            draws: 0,
+            played: 0,
+        }
+    }
+
+    // The participant sitting in each seat of the next game
+    pub fn seating(&self) -> Vec<usize> {
+        let participants = self.wins.len();
+        (0..participants).map(|seat| (seat + self.played) % participants.max(1)).collect()
+    }
+
+    // Call with each game once it is over, or has been given up as a draw
+    pub fn record(&mut self, game: &Game) {
+        let seating = self.seating();
+        match game.get_winner() {
+            Some(winner) => for id in game.winning_players(winner) {
+                if let Some(seat) = game.players().position(|(player, _)| player == id) {
+                    self.wins[seating[seat]] += 1;
+                }
+            },
+            None => self.draws += 1,
+        }
+        self.played += 1;
+    }
+
+    pub fn wins(&self) -> &[usize] {
+        &self.wins
+    }
+
+    pub fn draws(&self) -> usize {
+        self.draws
+    }
+
+    pub fn played(&self) -> usize {
+        self.played
+    }
+
+    // Whoever has won more than half of the N games, or leads on their own
+    // once all N have been played
+    pub fn winner(&self) -> Option<usize> {
+        let (leader, &most) = self.wins.iter().enumerate().max_by_key(|(_, wins)| **wins)?;
+        if most > self.best_of / 2 {
+            return Some(leader)
+        }
+        let alone = self.wins.iter().filter(|&&wins| wins == most).count() == 1;
+        Some(leader).filter(|_| self.played >= self.best_of && most > 0 && alone)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.played >= self.best_of || self.winner().is_some()
+    }
+}
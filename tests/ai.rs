@@ -0,0 +1,92 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use std::rc::Rc;
+
+use clonequest::ai::{DefensiveBot, LookaheadBot};
+use clonequest::controller::PlayerController;
+use clonequest::game::{Game, PlanetId, PlayerId};
+use clonequest::scoring::Scoring;
+
+mod common;
+
+use common::{edit, home, new_game, players};
+
+// Nothing anyone does changes the score
+struct Flat;
+
+impl Scoring for Flat {
+    fn score(&self, _game: &Game, _player: PlayerId) -> f64 {
+        0.0
+    }
+
+    fn describe(&self) -> String {
+        "nothing counts".to_string()
+    }
+}
+
+#[test]
+fn lookahead_bots_judge_by_the_games_scoring() {
+    let mut game = new_game(15);
+    let (alice, _) = players(&game);
+    assert!(!LookaheadBot::new(15).take_turn(&game, alice).is_empty());
+    // No order can beat sitting still when nothing scores
+    game.set_scoring(Rc::new(Flat));
+    assert!(LookaheadBot::new(15).take_turn(&game, alice).is_empty());
+}
+
+// Bob's home at the corner is attacked by 30 of Alice's ships due in
+// `turns`, more than either of his outposts one and two turns away can
+// make up for with the 20 ships each has to spare
+fn defence_drill(turns: usize) -> (Game, PlayerId, PlanetId, PlanetId, PlanetId) {
+    let game = new_game(16);
+    let (alice, bob) = players(&game);
+    let (alice_home, bob_home) = (home(&game, alice), home(&game, bob));
+    let planet = |index: usize| game.planets().nth(index).map(|(id, _)| id).unwrap();
+    let (near, far) = (planet(2), planet(3));
+    let positions = [(7, 7), (0, 0), (2, 0), (4, 0), (7, 0), (0, 7)];
+    let game = edit(&game, |value| {
+        for (index, pos) in positions.iter().enumerate() {
+            value["_planets"][index]["pos"] = serde_json::json!(pos);
+        }
+        for index in [2, 3] {
+            value["_planets"][index]["owner"] = serde_json::to_value(bob).unwrap();
+            value["_planets"][index]["ships"] = 30.into();
+        }
+        let fleet = serde_json::json!({
+            "id": value["_next_fleet_id"].clone(),
+            "ships": 30,
+            "strength": 40,
+            "turns_to_arrival": turns,
+            "source": serde_json::to_value(alice_home).unwrap(),
+            "destination": serde_json::to_value(bob_home).unwrap(),
+            "owner": serde_json::to_value(alice).unwrap(),
+            "name": null,
+            "veterancy": 0,
+        });
+        value["_fleets"].as_array_mut().unwrap().push(fleet);
+        value["_next_fleet_id"] = (value["_next_fleet_id"].as_u64().unwrap() + 1).into();
+    });
+    (game, bob, bob_home, near, far)
+}
+
+#[test]
+fn defensive_bots_reinforce_only_from_planets_that_land_first() {
+    let (game, bob, bob_home, near, far) = defence_drill(2);
+    let commands = DefensiveBot.take_turn(&game, bob);
+    let reinforcements: Vec<(PlanetId, usize)> = commands.iter()
+        .filter(|c| c.destination_planet_id == bob_home)
+        .map(|c| (c.source_planet_id, c.count))
+        .collect();
+    // The far outpost would land in the same turn as the attack
+    assert!(reinforcements == vec![(near, 20)]);
+    assert!(!reinforcements.iter().any(|&(source, _)| source == far));
+}
+
+#[test]
+fn defensive_bots_send_nothing_that_would_land_too_late() {
+    let (game, bob, bob_home, _, _) = defence_drill(1);
+    let commands = DefensiveBot.take_turn(&game, bob);
+    assert!(commands.iter().all(|c| c.destination_planet_id != bob_home));
+}
@@ -0,0 +1,87 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::game::{distance, Event, Message};
+
+mod common;
+
+use common::{edit, home, new_game, planet_index, players};
+
+fn position(events: &[Event], found: impl Fn(&Message) -> bool) -> Option<usize> {
+    events.iter().find(|event| found(&event.message)).map(|event| event.sequence)
+}
+
+#[test]
+fn reinforcements_landing_with_an_attack_arrive_after_the_battle() {
+    let game = new_game(8);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let trip = distance(game.planet(source).unwrap(), game.planet(target).unwrap());
+    // Bob gets a second planet closer to home than Alice is
+    let (outpost, _) = game.planets()
+        .filter(|(_, p)| p.owner.is_none())
+        .map(|(id, p)| (id, distance(p, game.planet(target).unwrap())))
+        .filter(|&(_, d)| d < trip)
+        .max_by_key(|&(_, d)| d)
+        .expect("A neutral planet lies close enough to Bob");
+    let outpost_index = planet_index(&game, outpost);
+    let mut game = edit(&game, |value| {
+        value["_planets"][outpost_index]["owner"] = serde_json::to_value(bob).unwrap();
+        value["_planets"][outpost_index]["ships"] = 10.into();
+    });
+    let reinforcement_trip = distance(game.planet(outpost).unwrap(), game.planet(target).unwrap());
+    game.queue_fleet(alice, source, target, 10, None).expect("Could not send fleet");
+    let mut rng = StdRng::seed_from_u64(8);
+    game.end_turn(&mut rng);
+    while game.fleets().any(|f| f.owner == alice && f.turns_to_arrival > reinforcement_trip) {
+        game.end_turn(&mut rng);
+    }
+    // Both fleets are due when the same turn ends
+    assert!(game.fleets().all(|f| f.turns_to_arrival == reinforcement_trip));
+    game.queue_fleet(bob, outpost, target, 5, None).expect("Could not send fleet");
+    let mut events = Vec::new();
+    while events.is_empty() || game.fleets().count() > 0 {
+        events = game.end_turn(&mut rng);
+    }
+    let battle = position(&events, |m| matches!(m, Message::Battle(_))).expect("Alice's fleet fought");
+    let reinforcement = position(&events, |m| matches!(m, Message::ReinforcementsArrived(_))).expect("Bob's fleet arrived");
+    assert!(battle < reinforcement);
+}
+
+#[test]
+fn battle_reports_match_the_fighting() {
+    let mut game = new_game(9);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    game.queue_fleet(alice, source, target, 10, None).expect("Could not send fleet");
+    let mut rng = StdRng::seed_from_u64(9);
+    game.end_turn(&mut rng);
+    while game.fleets().any(|f| f.turns_to_arrival > 1) {
+        game.end_turn(&mut rng);
+    }
+    let planet = game.planet(target).unwrap();
+    // Bob builds before the fleet lands
+    let garrison = planet.ships + planet.production;
+    let (alice_lost, bob_lost) = (game.stats(alice).ships_lost, game.stats(bob).ships_lost);
+    let events = game.end_turn(&mut rng);
+    let battle = events.iter().find_map(|event| match &event.message {
+        Message::Battle(battle) => Some(battle),
+        _ => None,
+    }).expect("Alice's fleet fought");
+    assert_eq!((battle.attackers, battle.defenders), (10, garrison));
+    assert!(battle.rounds > 0);
+    assert_eq!(game.stats(alice).ships_lost - alice_lost, battle.attacker_losses);
+    assert_eq!(game.stats(bob).ships_lost - bob_lost, battle.defender_losses);
+    let planet = game.planet(target).unwrap();
+    if battle.captured {
+        assert_eq!(battle.defender_losses, garrison);
+        assert_eq!(planet.ships, 10 - battle.attacker_losses);
+    } else {
+        assert_eq!(battle.attacker_losses, 10);
+        assert_eq!(planet.ships, garrison - battle.defender_losses);
+    }
+}
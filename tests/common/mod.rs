@@ -0,0 +1,81 @@
+// Helpers shared by the integration tests; not every test file uses all
+// of them
+#![allow(dead_code)]
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::cli::{Cli, Preferences};
+use clonequest::game::{Game, GameRules, PlanetId, Player, PlayerId};
+
+// Players with the given names, none of them on a team
+pub fn roster(names: &[&str]) -> Vec<Player> {
+    names.iter().map(|name| Player { name: name.to_string(), team: None }).collect()
+}
+
+pub fn new_game(seed: u64) -> Game {
+    new_game_with_rules(GameRules::default(), seed)
+}
+
+// Alice and Bob playing by the given rules
+pub fn new_game_with_rules(rules: GameRules, seed: u64) -> Game {
+    new_game_with(roster(&["Alice", "Bob"]), rules, seed)
+}
+
+// An 8 by 8 map with four neutral planets
+pub fn new_game_with(players: Vec<Player>, rules: GameRules, seed: u64) -> Game {
+    Game::new(8, 8, players, 4, rules, &mut StdRng::seed_from_u64(seed)).expect("Could not create game")
+}
+
+// Alice and Bob are on a team, Charlotte plays alone
+pub fn three_players(seed: u64) -> (Game, PlayerId, PlayerId, PlayerId) {
+    let mut players = roster(&["Alice", "Bob", "Charlotte"]);
+    players[0].team = Some(1);
+    players[1].team = Some(1);
+    let game = new_game_with(players, GameRules::default(), seed);
+    let ids: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+    (game, ids[0], ids[1], ids[2])
+}
+
+// Alice and Bob on a 12 by 12 map where Alice cannot see Bob's home
+// planet from hers
+pub fn foggy_game() -> Game {
+    let rules = GameRules { fog: Some(1), ..GameRules::default() };
+    let game = Game::new(12, 12, roster(&["Alice", "Bob"]), 6, rules, &mut StdRng::seed_from_u64(2)).expect("Could not create game");
+    let (alice, bob) = players(&game);
+    assert!(!game.visible(alice, home(&game, bob)));
+    game
+}
+
+pub fn home(game: &Game, player: PlayerId) -> PlanetId {
+    game.planets().find(|(_, p)| p.owner == Some(player)).map(|(id, _)| id).expect("Player has a planet")
+}
+
+pub fn players(game: &Game) -> (PlayerId, PlayerId) {
+    let ids: Vec<PlayerId> = game.players().map(|(id, _)| id).collect();
+    (ids[0], ids[1])
+}
+
+// Edits a game the way a hand-crafted or corrupted save file would
+pub fn edit(game: &Game, change: impl FnOnce(&mut serde_json::Value)) -> Game {
+    let mut value = serde_json::to_value(game).expect("Games serialize");
+    change(&mut value);
+    serde_json::from_value(value).expect("Edited game deserializes")
+}
+
+pub fn planet_index(game: &Game, id: PlanetId) -> usize {
+    game.planets().position(|(p, _)| p == id).expect("Planet exists")
+}
+
+// Types a command at the prompt
+pub fn run(cli: &mut Cli, line: &str) -> Result<(), String> {
+    cli.do_command(line.split_whitespace().map(|s| s.to_string()).collect())
+}
+
+// A prompt that never stops to ask for confirmation
+pub fn quiet_cli(game: Game) -> Cli {
+    let mut cli = Cli::new(game);
+    let quiet = Preferences { confirm_turn_end: false, predict_battles: false, idle_warning: None, ..Preferences::default() };
+    cli.set_preferences(quiet, None);
+    cli
+}
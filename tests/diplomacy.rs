@@ -0,0 +1,36 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::game::{Game, Message};
+
+mod common;
+
+use common::{home, new_game, players};
+
+#[test]
+fn surrendering_hands_everything_over_and_is_remembered() {
+    let mut game = new_game(10);
+    let (alice, bob) = players(&game);
+    let alice_home = home(&game, alice);
+    game.queue_fleet(alice, alice_home, home(&game, bob), 5, None).expect("Could not send fleet");
+    let mut rng = StdRng::seed_from_u64(10);
+    game.end_turn(&mut rng);
+    assert!(game.fleets().any(|f| f.owner == alice));
+    game.surrender(alice, bob).expect("Could not surrender");
+    let events = game.end_turn(&mut rng);
+    assert!(events.iter().any(|event| matches!(&event.message, Message::PlayerSurrendered(loser, winner) if loser.name == "Alice" && winner.name == "Bob")));
+    // A surrender is not an elimination
+    assert!(!events.iter().any(|event| matches!(event.message, Message::PlayerEliminated(_))));
+    assert!(game.planet(alice_home).unwrap().owner == Some(bob));
+    assert!(game.fleets().all(|f| f.owner == bob));
+    assert!(!game.remaining_players().contains(&alice));
+    assert!(game.vassals().eq(vec![(alice, bob)]));
+    // Vassalage survives saving and loading
+    let saved = serde_json::to_string(&game).expect("Games serialize");
+    let loaded: Game = serde_json::from_str(&saved).expect("Saved games load");
+    assert!(loaded.vassals().eq(vec![(alice, bob)]));
+}
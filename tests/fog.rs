@@ -0,0 +1,39 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+mod common;
+
+use common::{foggy_game, home, players};
+
+#[test]
+fn previews_leave_out_battles_in_the_fog() {
+    let mut game = foggy_game();
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let ships = game.planet(source).unwrap().ships;
+    game.queue_fleet(alice, source, target, ships, None).expect("Could not send fleet");
+    let mut rng = StdRng::seed_from_u64(2);
+    game.end_turn(&mut rng);
+    while game.fleets().any(|f| f.turns_to_arrival > 1) {
+        game.end_turn(&mut rng);
+    }
+    assert!(!game.visible(alice, target));
+    assert!(game.preview(alice).iter().all(|arrival| arrival.destination != target));
+}
+
+#[test]
+fn empires_are_only_counted_as_far_as_they_can_be_seen() {
+    let game = foggy_game();
+    let (alice, bob) = players(&game);
+    assert_eq!(game.empire_seen_by(alice, Some(bob)).planets, 0);
+    assert!(game.empire_seen_by(alice, Some(bob)).centre.is_none());
+    assert_eq!(game.empire_seen_by(alice, Some(alice)).planets, 1);
+    assert!(!game.shares_vision(alice, bob));
+    if let Some(prize) = game.largest_neutral_prize(alice) {
+        assert!(game.visible(alice, prize));
+    }
+}
@@ -0,0 +1,99 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::cli::Cli;
+use clonequest::game::{CouldNotCancel, CouldNotSend, Game, GameRules, PlayerId, ProtectionRules};
+
+mod common;
+
+use common::{home, new_game, new_game_with_rules, players, quiet_cli, run};
+
+#[test]
+fn home_planets_are_protected_for_the_first_turns() {
+    let rules = GameRules { protection: Some(ProtectionRules { turns: 2, radius: 0 }), ..GameRules::default() };
+    let mut game = new_game_with_rules(rules, 11);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let mut rng = StdRng::seed_from_u64(11);
+    for _ in 0..2 {
+        let result = game.queue_fleet(alice, source, target, 5, None);
+        assert!(matches!(result, Err(CouldNotSend::HomeProtected { until_turn: 2 })));
+        game.end_turn(&mut rng);
+    }
+    assert_eq!(game.fleets().count(), 0);
+    // Protection ends with turn 2
+    assert!(game.queue_fleet(alice, source, target, 5, None).is_ok());
+    assert_eq!(game.queued_commands(alice).count(), 1);
+}
+
+#[test]
+fn cancelled_orders_are_not_carried_out() {
+    let mut game = new_game(12);
+    let (alice, bob) = players(&game);
+    let (alice_home, bob_home) = (home(&game, alice), home(&game, bob));
+    let neutral = game.planets().find(|(_, p)| p.owner.is_none()).map(|(id, _)| id).expect("The map has neutral planets");
+    // Bob's order comes first, but only counts among his own
+    game.queue_fleet(bob, bob_home, neutral, 2, None).expect("Could not send fleet");
+    game.queue_fleet(alice, alice_home, neutral, 3, None).expect("Could not send fleet");
+    game.queue_fleet(alice, alice_home, neutral, 4, None).expect("Could not send fleet");
+    assert!(matches!(game.cancel_queued(alice, 2), Err(CouldNotCancel::NoSuchOrder)));
+    let cancelled = game.cancel_queued(alice, 0).expect("Could not cancel");
+    assert_eq!(cancelled.count, 3);
+    let left: Vec<usize> = game.queued_commands(alice).map(|c| c.count).collect();
+    assert_eq!(left, vec![4]);
+    assert_eq!(game.queued_commands(bob).count(), 1);
+    let built = |game: &Game, id| game.planet(id).map(|p| p.ships + p.production).unwrap();
+    let (alice_ships, bob_ships) = (built(&game, alice_home), built(&game, bob_home));
+    game.end_turn(&mut StdRng::seed_from_u64(12));
+    assert_eq!(game.planet(alice_home).unwrap().ships, alice_ships - 4);
+    assert_eq!(game.planet(bob_home).unwrap().ships, bob_ships - 2);
+}
+
+fn queued(cli: &Cli, player: PlayerId) -> Vec<usize> {
+    cli.game().queued_commands(player).map(|c| c.count).collect()
+}
+
+#[test]
+fn undo_and_redo_only_touch_the_current_players_orders() {
+    let game = new_game(13);
+    let (alice, bob) = players(&game);
+    let name = |id| game.planet(id).unwrap().name.clone();
+    let (alice_home, bob_home) = (name(home(&game, alice)), name(home(&game, bob)));
+    let send = |count: usize| format!("s {} {} {}", alice_home, bob_home, count);
+    let mut cli = quiet_cli(game);
+    run(&mut cli, &send(3)).unwrap();
+    run(&mut cli, &send(4)).unwrap();
+    run(&mut cli, "u").unwrap();
+    assert_eq!(queued(&cli, alice), vec![3]);
+    run(&mut cli, "u").unwrap();
+    assert!(run(&mut cli, "u").is_err());
+    run(&mut cli, "r").unwrap();
+    run(&mut cli, "r").unwrap();
+    assert_eq!(queued(&cli, alice), vec![3, 4]);
+    assert!(run(&mut cli, "r").is_err());
+    // A new order drops what was undone
+    run(&mut cli, "u").unwrap();
+    run(&mut cli, &send(5)).unwrap();
+    assert!(run(&mut cli, "r").is_err());
+    assert_eq!(queued(&cli, alice), vec![3, 5]);
+    // Bob cannot undo Alice's orders
+    run(&mut cli, "n").unwrap();
+    assert!(run(&mut cli, "u").is_err());
+    assert_eq!(queued(&cli, alice), vec![3, 5]);
+    assert!(queued(&cli, bob).is_empty());
+}
+
+#[test]
+fn planets_are_found_by_name_in_any_case() {
+    let game = new_game(17);
+    let (alice, _) = players(&game);
+    let id = home(&game, alice);
+    let name = game.planet(id).unwrap().name.clone();
+    assert!(game.get_planet_id(&name) == Ok(id));
+    assert!(game.get_planet_id(&name.to_ascii_lowercase()) == Ok(id));
+    assert!(game.get_planet_id("no planet").is_err());
+}
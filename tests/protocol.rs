@@ -0,0 +1,203 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde;
+extern crate serde_json;
+
+use std::collections::BTreeSet;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
+
+use clonequest::game::{BattleReport, Defence, Elimination, Event, Game, GameRules, Message, SendShipsCommand};
+use clonequest::protocol::{decode, encode, ClientMessage, CouldNotDecode, ProtocolError, ServerMessage, View, PROTOCOL_VERSION};
+
+mod common;
+
+use common::{foggy_game, home, new_game_with, players, roster};
+
+fn new_game() -> Game {
+    let mut players = roster(&["Alice", "Bob"]);
+    players[0].team = Some(1);
+    new_game_with(players, GameRules::default(), 1)
+}
+
+// A game with one of Alice's fleets on its way to Bob
+fn game_in_progress() -> Game {
+    let mut game = new_game();
+    let (alice, bob) = players(&game);
+    game.queue_fleet(alice, home(&game, alice), home(&game, bob), 5, Some("Vanguard".into()))
+        .expect("Could not send fleet");
+    game.end_turn(&mut StdRng::seed_from_u64(1));
+    assert_eq!(game.fleets().count(), 1);
+    game
+}
+
+// Decoding what was encoded must give back a message that encodes the same
+fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(message: T) -> String {
+    let text = encode(message);
+    let decoded: T = decode(&text).expect("Could not decode");
+    assert_eq!(encode(decoded), text);
+    text
+}
+
+// The matches below have no catch-all arm, so a new variant fails to
+// compile here until it has a sample
+fn message_name(message: &Message) -> &'static str {
+    match message {
+        Message::Battle(_) => "Battle",
+        Message::ReinforcementsArrived(_) => "ReinforcementsArrived",
+        Message::PlayerEliminated(_) => "PlayerEliminated",
+        Message::PlayerSurrendered(_, _) => "PlayerSurrendered",
+        Message::AllianceProposed(_, _) => "AllianceProposed",
+        Message::AllianceFormed(_, _) => "AllianceFormed",
+        Message::AllianceBroken(_, _) => "AllianceBroken",
+        Message::PlanetAttacked(_) => "PlanetAttacked",
+        Message::FinalScores(_) => "FinalScores",
+    }
+}
+
+fn client_name(message: &ClientMessage) -> &'static str {
+    match message {
+        ClientMessage::Join { .. } => "Join",
+        ClientMessage::SubmitCommands { .. } => "SubmitCommands",
+    }
+}
+
+fn server_name(message: &ServerMessage) -> &'static str {
+    match message {
+        ServerMessage::Lobby { .. } => "Lobby",
+        ServerMessage::TurnResolved { .. } => "TurnResolved",
+        ServerMessage::ViewUpdate(_) => "ViewUpdate",
+        ServerMessage::Error(_) => "Error",
+    }
+}
+
+fn error_name(error: &ProtocolError) -> &'static str {
+    match error {
+        ProtocolError::UnsupportedVersion { .. } => "UnsupportedVersion",
+        ProtocolError::GameFull => "GameFull",
+        ProtocolError::NameTaken => "NameTaken",
+        ProtocolError::NotInGame => "NotInGame",
+        ProtocolError::WrongTurn { .. } => "WrongTurn",
+        ProtocolError::InvalidCommand(_) => "InvalidCommand",
+    }
+}
+
+fn names<T>(samples: &[T], name: fn(&T) -> &'static str) -> BTreeSet<&'static str> {
+    samples.iter().map(name).collect()
+}
+
+fn expected(names: &[&'static str]) -> BTreeSet<&'static str> {
+    names.iter().cloned().collect()
+}
+
+fn sample_messages(game: &Game) -> Vec<Message> {
+    let (alice, bob) = players(game);
+    let fleet = game.fleets().next().expect("A fleet is in flight").clone();
+    let (a, b) = (game.player(alice).unwrap().clone(), game.player(bob).unwrap().clone());
+    vec![
+        Message::Battle(BattleReport {
+            fleet: fleet.clone(), defender: None, attackers: 5, defenders: 3, attacker_losses: 1, defender_losses: 3, rounds: 4, captured: true,
+        }),
+        Message::Battle(BattleReport {
+            fleet: fleet.clone(), defender: Some(bob), attackers: 5, defenders: 12, attacker_losses: 5, defender_losses: 2, rounds: 6, captured: false,
+        }),
+        Message::ReinforcementsArrived(fleet),
+        Message::PlayerEliminated(Elimination { player: b.clone(), by: Some(alice), turn: 3, ships_built: 20, ships_lost: 15, allies_remain: false }),
+        Message::PlayerEliminated(Elimination { player: b.clone(), by: None, turn: 3, ships_built: 0, ships_lost: 0, allies_remain: true }),
+        Message::PlayerSurrendered(b.clone(), a.clone()),
+        Message::AllianceProposed(a.clone(), b.clone()),
+        Message::AllianceFormed(a.clone(), b.clone()),
+        Message::AllianceBroken(b.clone(), a.clone()),
+        Message::PlanetAttacked(Defence { planet: home(game, bob), defender: bob, attacker: alice, held: true, ships_left: 4 }),
+        Message::FinalScores(vec![(a, 123.0), (b, 45.5)]),
+    ]
+}
+
+fn sample_errors() -> Vec<ProtocolError> {
+    vec![
+        ProtocolError::UnsupportedVersion { supported: PROTOCOL_VERSION },
+        ProtocolError::GameFull,
+        ProtocolError::NameTaken,
+        ProtocolError::NotInGame,
+        ProtocolError::WrongTurn { current: 7 },
+        ProtocolError::InvalidCommand("Not enough ships".into()),
+    ]
+}
+
+#[test]
+fn every_event_message_round_trips() {
+    let game = game_in_progress();
+    let messages = sample_messages(&game);
+    assert_eq!(names(&messages, message_name), expected(&[
+        "Battle", "ReinforcementsArrived", "PlayerEliminated", "PlayerSurrendered",
+        "AllianceProposed", "AllianceFormed", "AllianceBroken", "PlanetAttacked", "FinalScores",
+    ]));
+    let events = messages.into_iter().enumerate()
+        .map(|(sequence, message)| Event { turn: game.turn(), sequence, message })
+        .collect();
+    round_trip(ServerMessage::TurnResolved { turn: game.turn(), events });
+}
+
+#[test]
+fn every_client_message_round_trips() {
+    let game = game_in_progress();
+    let (alice, bob) = players(&game);
+    let order = SendShipsCommand { source_planet_id: home(&game, alice), destination_planet_id: home(&game, bob), count: 3, name: None };
+    let named = SendShipsCommand { name: Some("Second wave".into()), ..order.clone() };
+    let messages = vec![
+        ClientMessage::Join { name: "Alice".into() },
+        ClientMessage::SubmitCommands { turn: game.turn(), commands: vec![order, named] },
+        ClientMessage::SubmitCommands { turn: game.turn(), commands: Vec::new() },
+    ];
+    assert_eq!(names(&messages, client_name), expected(&["Join", "SubmitCommands"]));
+    for message in messages {
+        round_trip(message);
+    }
+}
+
+#[test]
+fn every_server_message_round_trips() {
+    let game = game_in_progress();
+    let (alice, _) = players(&game);
+    let mut messages = vec![
+        ServerMessage::Lobby { players: game.players().map(|(_, p)| p.clone()).collect(), seats: 4 },
+        ServerMessage::TurnResolved { turn: 1, events: Vec::new() },
+        ServerMessage::ViewUpdate(View::of(&game, alice)),
+    ];
+    let errors = sample_errors();
+    assert_eq!(names(&errors, error_name), expected(&[
+        "UnsupportedVersion", "GameFull", "NameTaken", "NotInGame", "WrongTurn", "InvalidCommand",
+    ]));
+    messages.extend(errors.into_iter().map(ServerMessage::Error));
+    assert_eq!(names(&messages, server_name), expected(&["Lobby", "TurnResolved", "ViewUpdate", "Error"]));
+    for message in messages {
+        round_trip(message);
+    }
+}
+
+#[test]
+fn views_hide_what_the_fog_hides() {
+    let game = foggy_game();
+    let (alice, bob) = players(&game);
+    let view = View::of(&game, alice);
+    assert!(view.planets.iter().all(|(id, _)| game.visible(alice, *id)));
+    assert!(view.planets.iter().any(|(id, _)| *id == home(&game, alice)));
+    assert!(view.planets.iter().all(|(id, _)| *id != home(&game, bob)));
+    round_trip(ServerMessage::ViewUpdate(view));
+}
+
+#[test]
+fn other_versions_are_refused() {
+    let text = encode(ClientMessage::Join { name: "Alice".into() })
+        .replace(&format!("\"version\":{}", PROTOCOL_VERSION), &format!("\"version\":{}", PROTOCOL_VERSION + 1));
+    assert!(matches!(decode::<ClientMessage>(&text), Err(CouldNotDecode::UnsupportedVersion(v)) if v == PROTOCOL_VERSION + 1));
+}
+
+#[test]
+fn malformed_messages_are_refused() {
+    assert!(matches!(decode::<ClientMessage>("{\"message\":{}}"), Err(CouldNotDecode::Malformed(_))));
+    let text = format!("{{\"version\":{},\"message\":{{\"Leave\":null}}}}", PROTOCOL_VERSION);
+    assert!(matches!(decode::<ClientMessage>(&text), Err(CouldNotDecode::Malformed(_))));
+}
@@ -0,0 +1,44 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use std::env;
+
+use clonequest::game::Game;
+
+mod common;
+
+use common::{home, new_game, players, quiet_cli, run};
+
+// Ships on every planet and in every fleet
+fn ships(game: &Game) -> (Vec<usize>, Vec<(usize, usize)>) {
+    (game.planets().map(|(_, p)| p.ships).collect(), game.fleets().map(|f| (f.ships, f.turns_to_arrival)).collect())
+}
+
+#[test]
+fn replays_of_games_loaded_mid_turn_give_orders_once() {
+    let game = new_game(14);
+    let (alice, bob) = players(&game);
+    let name = |id| game.planet(id).unwrap().name.clone();
+    let send = format!("s {} {} 6", name(home(&game, alice)), name(home(&game, bob)));
+    let path = env::temp_dir().join("clonequest-replay-mid-turn.json").to_string_lossy().into_owned();
+    let mut cli = quiet_cli(game);
+    cli.set_seed(14);
+    cli.record_replay("unused".into());
+    run(&mut cli, &send).expect("Alice can send the fleet");
+    run(&mut cli, "n").expect("Alice ends her turn");
+    // Bob saves and loads with Alice's order already queued
+    run(&mut cli, &format!("save {}", path)).expect("The game saves");
+    run(&mut cli, &format!("load {}", path)).expect("The game loads");
+    run(&mut cli, "n").expect("Bob ends his turn");
+    // One fleet of 6 left Alice's home
+    let alice_home = home(cli.game(), alice);
+    assert_eq!(cli.game().planet(alice_home).unwrap().ships, 14);
+    let replay = cli.replay().expect("The game is being recorded");
+    let mut replayed = replay.start.clone();
+    for turn in replay.turns.iter() {
+        turn.apply(&mut replayed).expect("The replay fits the game");
+    }
+    assert_eq!(ships(&replayed), ships(cli.game()));
+    let _ = std::fs::remove_file(&path);
+}
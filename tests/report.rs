@@ -0,0 +1,12 @@
+extern crate clonequest;
+
+use std::collections::HashSet;
+
+use clonequest::report::graph_symbol;
+
+#[test]
+fn every_player_gets_a_symbol_of_their_own() {
+    let symbols: HashSet<char> = (0..2000).map(graph_symbol).collect();
+    assert_eq!(symbols.len(), 2000);
+    assert!(!symbols.contains(&'?'));
+}
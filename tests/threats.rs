@@ -0,0 +1,36 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::game::{Game, PlayerId};
+
+mod common;
+
+use common::{home, three_players};
+
+fn send(game: &mut Game, from: PlayerId, to: PlayerId) {
+    let (source, target) = (home(game, from), home(game, to));
+    game.queue_fleet(from, source, target, 5, None).expect("Could not send fleet");
+}
+
+#[test]
+fn reinforcements_from_allies_leave_planets_idle() {
+    let (mut game, alice, bob, _) = three_players(3);
+    send(&mut game, bob, alice);
+    game.end_turn(&mut StdRng::seed_from_u64(3));
+    assert!(game.fleets().any(|f| f.owner == bob));
+    assert!(game.threats(alice).is_empty());
+    assert!(game.idle_planets(alice, 0).contains(&home(&game, alice)));
+}
+
+#[test]
+fn enemy_fleets_keep_planets_busy() {
+    let (mut game, alice, _, charlotte) = three_players(3);
+    send(&mut game, charlotte, alice);
+    game.end_turn(&mut StdRng::seed_from_u64(3));
+    assert_eq!(game.threats(alice).len(), 1);
+    assert!(!game.idle_planets(alice, 0).contains(&home(&game, alice)));
+}
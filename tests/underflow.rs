@@ -0,0 +1,133 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::game::{take_up_to, CouldNotSend, Message, SendShipsCommand};
+
+mod common;
+
+use common::{edit, home, new_game, planet_index, players};
+
+#[test]
+fn take_up_to_stops_at_zero() {
+    let mut count = 3;
+    assert_eq!(take_up_to(&mut count, 2), 2);
+    assert_eq!(count, 1);
+    assert_eq!(take_up_to(&mut count, 5), 1);
+    assert_eq!(count, 0);
+    assert_eq!(take_up_to(&mut count, 1), 0);
+    assert_eq!(count, 0);
+}
+
+#[test]
+fn sending_to_the_same_planet_is_rejected() {
+    let mut game = new_game(1);
+    let (alice, _) = players(&game);
+    let source = home(&game, alice);
+    let result = game.queue_fleet(alice, source, source, 1, None);
+    assert!(matches!(result, Err(CouldNotSend::SamePlanet)));
+    assert_eq!(game.queued_commands(alice).count(), 0);
+}
+
+#[test]
+fn checking_a_send_to_the_same_planet_fails() {
+    let game = new_game(6);
+    let (alice, _) = players(&game);
+    let source = home(&game, alice);
+    assert!(matches!(game.check_fleet(alice, source, source, 1, None), Err(CouldNotSend::SamePlanet)));
+}
+
+#[test]
+fn simulated_sends_to_the_same_planet_are_dropped() {
+    let game = new_game(7);
+    let (alice, _) = players(&game);
+    let source = home(&game, alice);
+    let order = SendShipsCommand { source_planet_id: source, destination_planet_id: source, count: 1, name: None };
+    let after = game.simulate(alice, &[order], 1, &mut StdRng::seed_from_u64(7));
+    assert_eq!(after.fleets().count(), 0);
+}
+
+#[test]
+fn sending_no_ships_is_rejected() {
+    let mut game = new_game(2);
+    let (alice, bob) = players(&game);
+    let result = game.queue_fleet(alice, home(&game, alice), home(&game, bob), 0, None);
+    assert!(matches!(result, Err(CouldNotSend::NoShips)));
+}
+
+#[test]
+fn fleets_due_this_turn_arrive_without_underflow() {
+    let game = new_game(3);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let target_index = planet_index(&game, target);
+    let mut game = edit(&game, |value| {
+        let fleet = serde_json::json!({
+            "id": value["_next_fleet_id"].clone(),
+            "ships": 50,
+            "strength": 50,
+            "turns_to_arrival": 0,
+            "source": serde_json::to_value(source).unwrap(),
+            "destination": serde_json::to_value(target).unwrap(),
+            "owner": serde_json::to_value(alice).unwrap(),
+            "name": null,
+            "veterancy": 0,
+        });
+        value["_fleets"].as_array_mut().unwrap().push(fleet);
+        value["_next_fleet_id"] = (value["_next_fleet_id"].as_u64().unwrap() + 1).into();
+        value["_planets"][target_index]["ships"] = 0.into();
+    });
+    game.end_turn(&mut StdRng::seed_from_u64(3));
+    assert_eq!(game.fleets().count(), 0);
+}
+
+#[test]
+fn queued_orders_larger_than_the_garrison_do_not_underflow() {
+    let mut game = new_game(4);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let ships = game.planet(source).unwrap().ships;
+    assert!(game.queue_fleet(alice, source, target, ships, None).is_ok());
+    let source_index = planet_index(&game, source);
+    // The garrison shrinks below what has already been queued
+    let mut game = edit(&game, |value| value["_planets"][source_index]["ships"] = 1.into());
+    assert!(matches!(game.check_fleet(alice, source, target, 1, None), Err(CouldNotSend::NotEnoughShips)));
+    game.preview(alice);
+    game.end_turn(&mut StdRng::seed_from_u64(4));
+    let launched: usize = game.fleets().filter(|f| f.owner == alice).map(|f| f.ships).sum();
+    let planet = game.planet(source).unwrap();
+    assert_eq!(launched, ships.min(1 + planet.production));
+    assert_eq!(launched + planet.ships, 1 + planet.production);
+}
+
+#[test]
+fn last_defender_falls_without_underflow() {
+    let game = new_game(5);
+    let (alice, bob) = players(&game);
+    let (source, target) = (home(&game, alice), home(&game, bob));
+    let target_index = planet_index(&game, target);
+    // Nothing is built on the target, so the fleet meets an empty garrison
+    let mut game = edit(&game, |value| {
+        value["_planets"][target_index]["ships"] = 0.into();
+        value["_planets"][target_index]["production"] = 0.into();
+    });
+    let ships = game.planet(source).unwrap().ships;
+    assert!(game.queue_fleet(alice, source, target, ships, None).is_ok());
+    let mut rng = StdRng::seed_from_u64(5);
+    let mut events = Vec::new();
+    while game.fleets().count() > 0 || game.turn() == 1 {
+        events.extend(game.end_turn(&mut rng));
+    }
+    let battle = events.iter().find_map(|event| match &event.message {
+        Message::Battle(battle) => Some(battle),
+        _ => None,
+    }).expect("The fleet fought for the planet");
+    assert!(battle.captured);
+    assert_eq!(battle.defenders, 0);
+    let planet = game.planet(target).unwrap();
+    assert!(planet.owner == Some(alice));
+    assert_eq!(planet.ships, ships - battle.attacker_losses);
+}
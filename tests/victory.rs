@@ -0,0 +1,151 @@
+extern crate clonequest;
+extern crate rand;
+extern crate serde_json;
+
+use std::rc::Rc;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+
+use clonequest::game::{Game, GameRules, Message, PlayerId, VictoryCondition, Winner};
+use clonequest::scoring::Scoring;
+
+mod common;
+
+use common::{edit, home, new_game_with, new_game_with_rules, planet_index, players, roster, three_players};
+
+fn ally(game: &mut Game, a: PlayerId, b: PlayerId) {
+    game.propose_alliance(a, b).expect("Could not propose");
+    game.propose_alliance(b, a).expect("Could not accept");
+    game.end_turn(&mut StdRng::seed_from_u64(1));
+    assert!(game.allied(a, b));
+}
+
+#[test]
+fn an_ally_of_an_ally_is_not_a_friend() {
+    let (mut game, alice, bob, charlotte) = three_players(1);
+    ally(&mut game, alice, charlotte);
+    assert!(!game.allied(bob, charlotte));
+    assert!(game.get_winner().is_none());
+}
+
+#[test]
+fn allies_left_on_their_own_win_together() {
+    let (mut game, alice, bob, charlotte) = three_players(1);
+    ally(&mut game, alice, charlotte);
+    ally(&mut game, bob, charlotte);
+    assert!(matches!(game.get_winner(), Some(Winner::Alliance(players)) if players == vec![alice, bob, charlotte]));
+}
+
+// Scores one player above everyone else, whatever happens
+struct Favourite(PlayerId);
+
+impl Scoring for Favourite {
+    fn score(&self, _game: &Game, player: PlayerId) -> f64 {
+        if player == self.0 { 1.0 } else { 0.0 }
+    }
+
+    fn describe(&self) -> String {
+        "one point for the favourite".to_string()
+    }
+}
+
+#[test]
+fn the_game_scoring_decides_the_turn_limit() {
+    let mut game = new_game_with_rules(GameRules { turn_limit: Some(1), ..GameRules::default() }, 1);
+    let (_, bob) = players(&game);
+    game.set_scoring(Rc::new(Favourite(bob)));
+    let events = game.end_turn(&mut StdRng::seed_from_u64(1));
+    let leader = events.iter().find_map(|event| match &event.message {
+        Message::FinalScores(scores) => scores.first().map(|(player, _)| player.name.clone()),
+        _ => None,
+    });
+    assert_eq!(leader.as_deref(), Some("Bob"));
+    assert!(game.get_winner() == Some(Winner::Player(bob)));
+}
+
+#[test]
+fn holding_the_hill_long_enough_wins() {
+    let rules = GameRules { victory: VictoryCondition::KingOfTheHill { turns: 2 }, ..GameRules::default() };
+    let game = new_game_with_rules(rules, 4);
+    let (alice, _) = players(&game);
+    let hill = game.hill().expect("King-of-the-hill games have a hill");
+    let hill_index = planet_index(&game, hill);
+    let mut game = edit(&game, |value| value["_planets"][hill_index]["owner"] = serde_json::to_value(alice).unwrap());
+    let mut rng = StdRng::seed_from_u64(4);
+    game.end_turn(&mut rng);
+    assert_eq!(game.planet(hill).unwrap().held_for, 1);
+    assert!(game.get_winner().is_none());
+    game.end_turn(&mut rng);
+    assert_eq!(game.planet(hill).unwrap().held_for, 2);
+    assert!(game.get_winner() == Some(Winner::Player(alice)));
+}
+
+#[test]
+fn losing_the_hill_starts_the_count_over() {
+    let rules = GameRules { victory: VictoryCondition::KingOfTheHill { turns: 100 }, ..GameRules::default() };
+    let game = new_game_with_rules(rules, 4);
+    let (alice, bob) = players(&game);
+    let hill = game.hill().expect("King-of-the-hill games have a hill");
+    let hill_index = planet_index(&game, hill);
+    // An undefended hill that cannot shoot back
+    let mut game = edit(&game, |value| {
+        value["_planets"][hill_index]["owner"] = serde_json::to_value(alice).unwrap();
+        value["_planets"][hill_index]["production"] = 0.into();
+        value["_planets"][hill_index]["strength"] = 0.into();
+    });
+    game.queue_fleet(bob, home(&game, bob), hill, 10, None).expect("Could not send fleet");
+    let mut rng = StdRng::seed_from_u64(4);
+    game.end_turn(&mut rng);
+    while game.fleets().count() > 0 {
+        assert!(game.planet(hill).unwrap().held_for > 0);
+        game.end_turn(&mut rng);
+    }
+    let planet = game.planet(hill).unwrap();
+    assert!(planet.owner == Some(bob));
+    assert_eq!(planet.held_for, 0);
+    game.end_turn(&mut rng);
+    assert_eq!(game.planet(hill).unwrap().held_for, 1);
+}
+
+#[test]
+fn the_first_to_build_the_target_wins() {
+    let rules = GameRules { victory: VictoryCondition::Economic { target: 25 }, ..GameRules::default() };
+    let game = new_game_with_rules(rules, 4);
+    let (alice, bob) = players(&game);
+    let bob_index = planet_index(&game, home(&game, bob));
+    let mut game = edit(&game, |value| value["_planets"][bob_index]["production"] = 20.into());
+    let mut rng = StdRng::seed_from_u64(4);
+    game.end_turn(&mut rng);
+    assert_eq!((game.produced(alice), game.produced(bob)), (10, 20));
+    assert_eq!(game.stats(bob).ships_built, 20);
+    assert!(game.get_winner().is_none());
+    game.end_turn(&mut rng);
+    assert_eq!((game.produced(alice), game.produced(bob)), (20, 40));
+    assert!(game.get_winner() == Some(Winner::Player(bob)));
+}
+
+#[test]
+fn builders_passing_the_target_together_are_split_by_ships_built() {
+    let rules = GameRules { victory: VictoryCondition::Economic { target: 15 }, ..GameRules::default() };
+    let game = new_game_with_rules(rules, 4);
+    let (alice, bob) = players(&game);
+    let alice_index = planet_index(&game, home(&game, alice));
+    let mut game = edit(&game, |value| value["_planets"][alice_index]["production"] = 9.into());
+    let mut rng = StdRng::seed_from_u64(4);
+    game.end_turn(&mut rng);
+    assert!(game.get_winner().is_none());
+    game.end_turn(&mut rng);
+    assert_eq!((game.produced(alice), game.produced(bob)), (18, 20));
+    assert!(game.get_winner() == Some(Winner::Player(bob)));
+}
+
+#[test]
+fn players_out_of_the_game_cannot_win_on_score() {
+    let mut game = new_game_with(roster(&["Alice", "Bob", "Charlotte"]), GameRules { turn_limit: Some(1), ..GameRules::default() }, 1);
+    let (alice, bob) = players(&game);
+    game.set_scoring(Rc::new(Favourite(alice)));
+    game.forfeit(alice);
+    game.end_turn(&mut StdRng::seed_from_u64(1));
+    assert!(game.get_winner() == Some(Winner::Player(bob)));
+}